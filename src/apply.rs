@@ -0,0 +1,361 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::paths::{absolutize, resolves_inside};
+
+/// A change to one file, as produced by a model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// A unified diff for one file. `old`/`new` are None for `/dev/null`, i.e. when
+    /// the diff creates or deletes the file.
+    Diff {
+        old: Option<String>,
+        new: Option<String>,
+        hunks: Vec<Hunk>,
+    },
+    /// An aider-style `<<<<<<< SEARCH` / `=======` / `>>>>>>> REPLACE` block.
+    Replace {
+        path: String,
+        search: String,
+        replace: String,
+    },
+}
+
+impl Edit {
+    pub fn path(&self) -> &str {
+        match self {
+            Edit::Diff { old, new, .. } => new.as_deref().or(old.as_deref()).unwrap_or(""),
+            Edit::Replace { path, .. } => path,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    /// 1-based line the hunk starts at in the original file.
+    pub old_start: usize,
+    /// Lines with their ' ', '-' or '+' marker.
+    pub lines: Vec<(char, String)>,
+}
+
+lazy_static! {
+    static ref HUNK_HEADER: Regex =
+        Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@").unwrap();
+}
+
+/// Pull every unified diff and search/replace block out of `text`, in order.
+pub fn parse_edits(text: &str) -> Vec<Edit> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut edits = Vec::new();
+    let mut i = 0;
+    // Where the last search/replace block ended, and the file it was for
+    let mut after_block = 0;
+    let mut last_path = String::new();
+
+    while i < lines.len() {
+        let line = lines[i];
+        if line.starts_with("--- ") && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ ")) {
+            let old = diff_path(&line[4..]);
+            let new = diff_path(&lines[i + 1][4..]);
+            i += 2;
+            let mut hunks = Vec::new();
+            while let Some((old_start, mut old_left, mut new_left)) =
+                lines.get(i).and_then(|l| hunk_header(l))
+            {
+                i += 1;
+                let mut hunk_lines = Vec::new();
+                while (old_left > 0 || new_left > 0) && i < lines.len() {
+                    let l = lines[i];
+                    let (marker, body) = match l.chars().next() {
+                        // Some tools drop the space on blank context lines
+                        None => (' ', ""),
+                        Some(c @ (' ' | '-' | '+')) => (c, &l[1..]),
+                        Some('\\') => {
+                            i += 1;
+                            continue;
+                        }
+                        _ => break,
+                    };
+                    if marker != '+' {
+                        old_left = old_left.saturating_sub(1);
+                    }
+                    if marker != '-' {
+                        new_left = new_left.saturating_sub(1);
+                    }
+                    hunk_lines.push((marker, body.to_string()));
+                    i += 1;
+                }
+                hunks.push(Hunk {
+                    old_start,
+                    lines: hunk_lines,
+                });
+            }
+            edits.push(Edit::Diff { old, new, hunks });
+        } else if line.trim_end() == "<<<<<<< SEARCH" {
+            // The file name comes just before the block; consecutive blocks for the
+            // same file may leave it out
+            let path = lines[after_block..i]
+                .iter()
+                .rev()
+                .map(|l| l.trim())
+                .find(|l| !l.is_empty() && !l.starts_with("```") && !is_marker(l))
+                .map(clean_path)
+                .unwrap_or_else(|| last_path.clone());
+            let Some(sep) = (i + 1..lines.len()).find(|&j| lines[j].trim_end() == "=======") else {
+                break;
+            };
+            let Some(end) =
+                (sep + 1..lines.len()).find(|&j| lines[j].trim_end() == ">>>>>>> REPLACE")
+            else {
+                break;
+            };
+            last_path = path.clone();
+            after_block = end + 1;
+            edits.push(Edit::Replace {
+                path,
+                search: join_lines(&lines[i + 1..sep]),
+                replace: join_lines(&lines[sep + 1..end]),
+            });
+            i = end + 1;
+        } else {
+            i += 1;
+        }
+    }
+    edits
+}
+
+// `@@ -3,4 +3,5 @@` -> (old start, old count, new count). Counts default to 1. A
+// header whose numbers don't fit is not treated as a hunk at all.
+fn hunk_header(line: &str) -> Option<(usize, usize, usize)> {
+    let caps = HUNK_HEADER.captures(line)?;
+    let count = |n: usize| caps.get(n).map_or(Some(1), |m| m.as_str().parse().ok());
+    Some((caps[1].parse().ok()?, count(2)?, count(4)?))
+}
+
+// "a/src/lib.rs\t2024-01-01 ..." -> "src/lib.rs"; "/dev/null" -> None
+fn diff_path(raw: &str) -> Option<String> {
+    let path = raw.split('\t').next().unwrap().trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+// A search/replace or merge conflict marker line, never a file name.
+fn is_marker(line: &str) -> bool {
+    ["<<<<<<<", "=======", ">>>>>>>"]
+        .iter()
+        .any(|m| line.starts_with(m))
+}
+
+fn clean_path(line: &str) -> String {
+    line.trim_start_matches('#')
+        .trim()
+        .trim_end_matches(':')
+        .trim_matches(|c| c == '*' || c == '`')
+        .to_string()
+}
+
+fn join_lines(lines: &[&str]) -> String {
+    lines.iter().map(|l| format!("{}\n", l)).collect()
+}
+
+/// Outcome of applying all edits for one file.
+#[derive(Clone, Debug, Default)]
+pub struct ApplyReport {
+    pub path: String,
+    pub applied: usize,
+    /// One message per hunk or block that did not apply.
+    pub failures: Vec<String>,
+}
+
+/// Apply `edits` to files resolved against `base`. Every target must lie inside one
+/// of `roots` (the paths the prompt was built from); edits for other files are
+/// reported as failures and never touch the disk. Hunks that don't match are
+/// reported while the rest of the file's edits still apply.
+pub fn apply_edits(
+    edits: &[Edit],
+    base: &Path,
+    roots: &[PathBuf],
+    dry_run: bool,
+) -> Result<Vec<ApplyReport>, Box<dyn Error>> {
+    let roots: Vec<PathBuf> = roots.iter().map(|r| absolutize(&base.join(r))).collect();
+
+    // Group edits by file, keeping the order files first appear in
+    let mut by_file: Vec<(String, Vec<&Edit>)> = Vec::new();
+    for edit in edits {
+        match by_file.iter_mut().find(|(p, _)| p == edit.path()) {
+            Some((_, list)) => list.push(edit),
+            None => by_file.push((edit.path().to_string(), vec![edit])),
+        }
+    }
+
+    let mut reports = Vec::new();
+    for (path, file_edits) in by_file {
+        let mut report = ApplyReport {
+            path: path.clone(),
+            ..Default::default()
+        };
+        let target = absolutize(&base.join(&path));
+        let inside = roots
+            .iter()
+            .any(|r| target.starts_with(r) && resolves_inside(&target, r));
+        if path.is_empty() || !inside {
+            report
+                .failures
+                .push("path is outside the input roots".to_string());
+            reports.push(report);
+            continue;
+        }
+
+        let mut content = fs::read_to_string(&target).ok();
+        for edit in file_edits {
+            apply_one(edit, &mut content, &mut report);
+        }
+
+        if !dry_run && report.applied > 0 {
+            match &content {
+                Some(text) => {
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&target, text)
+                        .map_err(|e| format!("Cannot write {}: {}", target.display(), e))?;
+                }
+                None => fs::remove_file(&target)
+                    .map_err(|e| format!("Cannot delete {}: {}", target.display(), e))?,
+            }
+        }
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+// `content` is None when the file doesn't exist (or is deleted by the edit).
+fn apply_one(edit: &Edit, content: &mut Option<String>, report: &mut ApplyReport) {
+    match edit {
+        Edit::Replace {
+            search, replace, ..
+        } => match content {
+            None if search.is_empty() => {
+                *content = Some(replace.clone());
+                report.applied += 1;
+            }
+            None => report.failures.push("file does not exist".to_string()),
+            Some(text) => {
+                if let Some(pos) = text.find(search.as_str()) {
+                    text.replace_range(pos..pos + search.len(), replace);
+                    report.applied += 1;
+                } else {
+                    let first = search.lines().next().unwrap_or("");
+                    report
+                        .failures
+                        .push(format!("search block not found: {:?}", first));
+                }
+            }
+        },
+        Edit::Diff { new: None, .. } => {
+            if content.take().is_some() {
+                report.applied += 1;
+            } else {
+                report
+                    .failures
+                    .push("file to delete does not exist".to_string());
+            }
+        }
+        // e.g. every header was malformed, like `@@ -99999999999999999999999 +1 @@`
+        Edit::Diff {
+            old: Some(_),
+            hunks,
+            ..
+        } if hunks.is_empty() => report.failures.push("no readable hunks".to_string()),
+        Edit::Diff { old, hunks, .. } => {
+            let original = match (old, content.as_deref()) {
+                (None, None) => String::new(),
+                (None, Some(_)) => {
+                    report
+                        .failures
+                        .push("file to create already exists".to_string());
+                    return;
+                }
+                (Some(_), None) => {
+                    report.failures.push("file does not exist".to_string());
+                    return;
+                }
+                (Some(_), Some(text)) => text.to_string(),
+            };
+            let had_newline = original.ends_with('\n') || original.is_empty();
+            let mut lines: Vec<String> = original.lines().map(str::to_string).collect();
+            let mut offset: isize = 0;
+            for (n, hunk) in hunks.iter().enumerate() {
+                match apply_hunk(&mut lines, hunk, offset) {
+                    Some(delta) => {
+                        offset += delta;
+                        report.applied += 1;
+                    }
+                    None => report.failures.push(format!(
+                        "hunk #{} (@@ -{}) does not match",
+                        n + 1,
+                        hunk.old_start
+                    )),
+                }
+            }
+            let mut text = lines.join("\n");
+            if had_newline && !text.is_empty() {
+                text.push('\n');
+            }
+            *content = Some(text);
+        }
+    }
+}
+
+// Find where the hunk's old lines sit, preferring the position its header claims
+// (adjusted for earlier hunks), and splice in the new lines. Returns the change in
+// line count, or None if the old lines can't be found.
+fn apply_hunk(lines: &mut Vec<String>, hunk: &Hunk, offset: isize) -> Option<isize> {
+    let old: Vec<&str> = hunk
+        .lines
+        .iter()
+        .filter(|(m, _)| *m != '+')
+        .map(|(_, l)| l.as_str())
+        .collect();
+    let new: Vec<String> = hunk
+        .lines
+        .iter()
+        .filter(|(m, _)| *m != '-')
+        .map(|(_, l)| l.clone())
+        .collect();
+
+    // For pure insertions the header names the line after which to insert
+    let expected = if old.is_empty() {
+        hunk.old_start as isize + offset
+    } else {
+        hunk.old_start as isize - 1 + offset
+    };
+    let expected = expected.clamp(0, lines.len() as isize) as usize;
+
+    let matches_at = |pos: usize| {
+        pos + old.len() <= lines.len()
+            && lines[pos..pos + old.len()]
+                .iter()
+                .zip(&old)
+                .all(|(a, b)| a.trim_end() == b.trim_end())
+    };
+    let pos = (0..=lines.len()).find_map(|d| {
+        [expected.checked_sub(d), Some(expected + d)]
+            .into_iter()
+            .flatten()
+            .find(|&p| matches_at(p))
+    })?;
+
+    lines.splice(pos..pos + old.len(), new.iter().cloned());
+    Some(new.len() as isize - old.len() as isize)
+}
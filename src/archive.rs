@@ -0,0 +1,81 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+/// Archive formats we can read members from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+/// Recognise an archive by its file name.
+pub fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else {
+        None
+    }
+}
+
+/// Call `f` with the path, declared size and contents of every regular file in the
+/// archive, in archive order. Nothing is read until `f` reads it, so a member can be
+/// skipped by its size alone. The size comes from the archive's headers and may be
+/// wrong. Directories, links and members with unsafe paths are skipped.
+pub fn for_each_member(
+    path: &Path,
+    kind: ArchiveKind,
+    mut f: impl FnMut(&str, u64, &mut dyn Read) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let file = File::open(path)?;
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = zip::ZipArchive::new(BufReader::new(file))
+                .map_err(|e| format!("Cannot read zip archive {}: {}", path.display(), e))?;
+            for i in 0..zip.len() {
+                let mut member = zip.by_index(i)?;
+                if !member.is_file() {
+                    continue;
+                }
+                let Some(name) = member.enclosed_name() else {
+                    continue;
+                };
+                let name = name.to_string_lossy().into_owned();
+                f(&name, member.size(), &mut member)?;
+            }
+        }
+        ArchiveKind::Tar => read_tar(BufReader::new(file), &mut f)?,
+        ArchiveKind::TarGz => read_tar(GzDecoder::new(BufReader::new(file)), &mut f)?,
+    }
+    Ok(())
+}
+
+fn read_tar(
+    reader: impl Read,
+    f: &mut impl FnMut(&str, u64, &mut dyn Read) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        // Mirror zip's enclosed_name(): no absolute paths or `..` escapes.
+        if path.is_absolute() || path.components().any(|c| c.as_os_str() == "..") {
+            continue;
+        }
+        let name = path.to_string_lossy().trim_start_matches("./").to_string();
+        f(&name, entry.size(), &mut entry)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::redact::SecretCounts;
+
+/// A document after the content transforms, as stored in the cache.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CachedDoc {
+    pub content: String,
+    pub tokens: usize,
+    /// Secrets the redactor replaced, so warnings and `--fail-on-secrets` still work
+    /// on a cache hit.
+    pub secrets: SecretCounts,
+}
+
+/// On-disk cache of transformed content and token counts, so repeated runs over a
+/// mostly unchanged tree skip outlining, comment stripping and secret scanning.
+/// Entries are JSON files named after the hash of their key.
+#[derive(Clone, Debug)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    pub fn new(dir: impl Into<PathBuf>) -> DiskCache {
+        DiskCache { dir: dir.into() }
+    }
+
+    /// `$FILES_TO_PROMPT_CACHE_DIR`, or `files-to-prompt` under `$XDG_CACHE_HOME` or
+    /// `~/.cache`.
+    pub fn default_dir() -> Option<PathBuf> {
+        let non_empty = |name| {
+            env::var_os(name)
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+        };
+        non_empty("FILES_TO_PROMPT_CACHE_DIR").or_else(|| {
+            non_empty("XDG_CACHE_HOME")
+                .or_else(|| non_empty("HOME").map(|home| home.join(".cache")))
+                .map(|dir| dir.join("files-to-prompt"))
+        })
+    }
+
+    /// The key for `content` read from `path`, transformed with the options
+    /// described by `options`. `source` is the file's metadata when it is a file on
+    /// disk rather than an archive member.
+    pub fn key(path: &Path, source: Option<&fs::Metadata>, content: &str, options: &str) -> String {
+        let modified = source
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_nanos());
+        let size = source.map_or(0, |m| m.len());
+
+        let mut hasher = Sha256::new();
+        for part in [
+            env!("CARGO_PKG_VERSION"),
+            &path.to_string_lossy(),
+            &modified.to_string(),
+            &size.to_string(),
+            options,
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        hasher.update(Sha256::digest(content.as_bytes()));
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedDoc> {
+        let text = fs::read_to_string(self.entry_path(key)).ok()?;
+        let value: Value = serde_json::from_str(&text).ok()?;
+        let secrets = value["secrets"]
+            .as_object()?
+            .iter()
+            .map(|(kind, n)| Some((kind.clone(), n.as_u64()? as usize)))
+            .collect::<Option<_>>()?;
+        Some(CachedDoc {
+            content: value["content"].as_str()?.to_string(),
+            tokens: value["tokens"].as_u64()? as usize,
+            secrets,
+        })
+    }
+
+    pub fn put(&self, key: &str, doc: &CachedDoc) -> io::Result<()> {
+        let path = self.entry_path(key);
+        fs::create_dir_all(path.parent().unwrap())?;
+        let value = json!({
+            "content": doc.content,
+            "tokens": doc.tokens,
+            "secrets": doc.secrets,
+        });
+        // Write then rename, so a concurrent run never reads half an entry
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp, value.to_string())?;
+        fs::rename(&tmp, &path)
+    }
+
+    /// Delete every entry and return how many there were. Only the shard directories
+    /// and the files this cache writes are touched, so pointing the cache at a
+    /// directory that holds other things is harmless.
+    pub fn clear(&self) -> io::Result<usize> {
+        if !self.dir.is_dir() {
+            return Ok(0);
+        }
+        let mut count = 0;
+        for shard in fs::read_dir(&self.dir)? {
+            let shard = shard?;
+            let name = shard.file_name();
+            if !shard.file_type()?.is_dir() || !is_hex(&name.to_string_lossy(), 2) {
+                continue;
+            }
+            for entry in fs::read_dir(shard.path())? {
+                let entry = entry?;
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                let Some((stem, ext)) = name.split_once('.') else {
+                    continue;
+                };
+                if !entry.file_type()?.is_file() || !is_hex(stem, 64) {
+                    continue;
+                }
+                if ext == "json" {
+                    fs::remove_file(entry.path())?;
+                    count += 1;
+                } else if ext.starts_with("tmp") {
+                    fs::remove_file(entry.path())?;
+                }
+            }
+            // Leave the shard (and the cache directory) alone if anything else is in it
+            let _ = fs::remove_dir(shard.path());
+        }
+        let _ = fs::remove_dir(&self.dir);
+        Ok(count)
+    }
+
+    // Entries are spread over subdirectories named after the first two hex digits.
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(&key[..2]).join(format!("{}.json", key))
+    }
+}
+
+fn is_hex(name: &str, len: usize) -> bool {
+    name.len() == len && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
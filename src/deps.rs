@@ -0,0 +1,277 @@
+use std::collections::{HashSet, VecDeque};
+use std::path::{Component, Path, PathBuf};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::lang::language_for;
+
+lazy_static! {
+    static ref RUST_MOD: Regex =
+        Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(\w+)\s*;").unwrap();
+    static ref RUST_CRATE_PATH: Regex = Regex::new(r"\bcrate::").unwrap();
+    static ref PY_FROM_IMPORT: Regex = Regex::new(
+        r"(?m)^[ \t]*from[ \t]+(\.+)([\w.]*)[ \t]+import[ \t]+(?:\(([^)]*)\)|([\w \t,]+))"
+    )
+    .unwrap();
+    static ref JS_IMPORT: Regex =
+        Regex::new(r#"(?:\bfrom\s*|\bimport\s*\(?\s*|\brequire\s*\(\s*)["'](\.{1,2}/[^"']*)["']"#)
+            .unwrap();
+}
+
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+/// Starting from `entries`, collect every file reachable through Rust `mod`
+/// declarations and `crate::` paths, and relative Python and JS/TS imports, going at
+/// most `max_depth` hops (unlimited when None). Entries come first, then
+/// dependencies in breadth-first order, each file once. Directories in `entries` are
+/// passed through untouched.
+pub fn follow_deps(entries: &[String], max_depth: Option<usize>) -> Vec<String> {
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let mut out = Vec::new();
+    let mut queue: VecDeque<(PathBuf, usize)> = VecDeque::new();
+
+    for entry in entries {
+        let path = Path::new(entry);
+        if !path.is_file() {
+            out.push(entry.clone());
+        } else if seen.insert(normalize(path)) {
+            out.push(entry.clone());
+            queue.push_back((path.to_path_buf(), 0));
+        }
+    }
+
+    while let Some((file, depth)) = queue.pop_front() {
+        if max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        for dep in dependencies(&file, &content) {
+            let dep = normalize(&dep);
+            if seen.insert(dep.clone()) {
+                out.push(dep.to_string_lossy().into_owned());
+                queue.push_back((dep, depth + 1));
+            }
+        }
+    }
+    out
+}
+
+/// Files that `file` directly depends on, in the order they are mentioned.
+pub fn dependencies(file: &Path, content: &str) -> Vec<PathBuf> {
+    match language_for(file) {
+        Some("rust") => rust_deps(file, content),
+        Some("python") => python_deps(file, content),
+        Some("javascript") | Some("typescript") => js_deps(file, content),
+        _ => Vec::new(),
+    }
+}
+
+fn rust_deps(file: &Path, content: &str) -> Vec<PathBuf> {
+    let mut deps = Vec::new();
+
+    // `mod foo;` lives next to a crate root or mod.rs, or in a directory named
+    // after the current file otherwise
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let stem = file.file_stem().unwrap_or_default();
+    let mod_dir = if ["main", "lib", "mod"].iter().any(|s| stem == *s) {
+        dir.to_path_buf()
+    } else {
+        dir.join(stem)
+    };
+    for caps in RUST_MOD.captures_iter(content) {
+        deps.extend(rust_module_file(&mod_dir, &caps[1]));
+    }
+
+    // `crate::a::b::Thing` resolves to the deepest module file that exists. A binary
+    // refers to its own package's library by name instead, e.g. `my_tool::a::b`.
+    if let Some(root) = crate_root(file) {
+        let lib_path = lib_name(&root).and_then(|name| Regex::new(&format!(r"\b{}::", name)).ok());
+        let prefixes = [Some(&*RUST_CRATE_PATH), lib_path.as_ref()];
+        for m in prefixes
+            .into_iter()
+            .flatten()
+            .flat_map(|re| re.find_iter(content))
+        {
+            for segments in use_paths(&content[m.end()..]) {
+                let mut found = None;
+                let mut dir = root.clone();
+                for segment in &segments {
+                    match rust_module_file(&dir, segment) {
+                        Some(f) => {
+                            dir = dir.join(segment);
+                            found = Some(f);
+                        }
+                        None => break,
+                    }
+                }
+                deps.extend(found);
+            }
+        }
+    }
+    deps
+}
+
+// `dir/name.rs` or `dir/name/mod.rs`
+fn rust_module_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    [
+        dir.join(format!("{}.rs", name)),
+        dir.join(name).join("mod.rs"),
+    ]
+    .into_iter()
+    .find(|p| p.is_file())
+}
+
+// The directory holding main.rs/lib.rs for the crate `file` belongs to: the `src`
+// directory next to the nearest Cargo.toml.
+fn crate_root(file: &Path) -> Option<PathBuf> {
+    file.ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(|dir| dir.join("src"))
+        .filter(|src| src.is_dir())
+}
+
+// The library crate name (`my-tool` becomes `my_tool`), if the package has a lib.rs.
+fn lib_name(root: &Path) -> Option<String> {
+    if !root.join("lib.rs").is_file() {
+        return None;
+    }
+    let manifest = std::fs::read_to_string(root.parent()?.join("Cargo.toml")).ok()?;
+    let name = manifest
+        .lines()
+        .skip_while(|l| l.trim() != "[package]")
+        .find_map(|l| {
+            let (key, value) = l.split_once('=')?;
+            (key.trim() == "name").then(|| value.trim().trim_matches('"').to_string())
+        })?;
+    Some(name.replace('-', "_"))
+}
+
+// Expand the path after `crate::` into module paths:
+// `a::b::C` -> [[a, b, C]], `{a::X, b::{c, d}}` -> [[a, X], [b, c], [b, d]]
+fn use_paths(text: &str) -> Vec<Vec<String>> {
+    let mut paths = Vec::new();
+    expand_use_tree(text, &mut Vec::new(), &mut paths);
+    paths
+}
+
+fn expand_use_tree(text: &str, prefix: &mut Vec<String>, out: &mut Vec<Vec<String>>) -> usize {
+    let mut i = 0;
+    let bytes = text.as_bytes();
+    let depth = prefix.len();
+    loop {
+        let start = i;
+        while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+            i += 1;
+        }
+        if i > start {
+            prefix.push(text[start..i].to_string());
+        }
+        if text[i..].starts_with("::") {
+            i += 2;
+            continue;
+        }
+        if bytes.get(i) == Some(&b'{') {
+            i += 1;
+            loop {
+                while bytes
+                    .get(i)
+                    .is_some_and(|b| b.is_ascii_whitespace() || *b == b',')
+                {
+                    i += 1;
+                }
+                if i >= bytes.len() || bytes[i] == b'}' {
+                    i += 1;
+                    break;
+                }
+                let before = i;
+                i += expand_use_tree(&text[i..], prefix, out);
+                if i == before {
+                    // Step over whatever it was, which may be more than one byte
+                    i += text[i..].chars().next().map_or(1, char::len_utf8);
+                }
+            }
+        } else if prefix.len() > depth {
+            out.push(prefix.clone());
+        }
+        prefix.truncate(depth);
+        return i.min(bytes.len());
+    }
+}
+
+fn python_deps(file: &Path, content: &str) -> Vec<PathBuf> {
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let mut deps = Vec::new();
+    for caps in PY_FROM_IMPORT.captures_iter(content) {
+        // One dot is the current package, each extra dot goes up a level
+        let mut base = dir.to_path_buf();
+        for _ in 1..caps[1].len() {
+            base.push("..");
+        }
+        let module = &caps[2];
+        if module.is_empty() {
+            // from . import a, b
+            let names = caps.get(3).or(caps.get(4)).map_or("", |m| m.as_str());
+            for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                deps.extend(python_module_file(&base, name));
+            }
+        } else {
+            deps.extend(python_module_file(&base, module));
+        }
+    }
+    deps
+}
+
+// `base/a/b.py` or `base/a/b/__init__.py` for module `a.b`
+fn python_module_file(base: &Path, module: &str) -> Option<PathBuf> {
+    let path = module
+        .split('.')
+        .fold(base.to_path_buf(), |p, part| p.join(part));
+    [path.with_extension("py"), path.join("__init__.py")]
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+fn js_deps(file: &Path, content: &str) -> Vec<PathBuf> {
+    let dir = file.parent().unwrap_or(Path::new(""));
+    JS_IMPORT
+        .captures_iter(content)
+        .filter_map(|caps| {
+            let target = dir.join(&caps[1]);
+            std::iter::once(target.clone())
+                .chain(JS_EXTENSIONS.iter().map(|ext| {
+                    let mut name = target.clone().into_os_string();
+                    name.push(format!(".{}", ext));
+                    PathBuf::from(name)
+                }))
+                .chain(
+                    JS_EXTENSIONS
+                        .iter()
+                        .map(|ext| target.join(format!("index.{}", ext))),
+                )
+                .find(|p| p.is_file())
+        })
+        .collect()
+}
+
+// Resolve `.` and `..` without touching the file system, so `src/a/../b.rs` and
+// `src/b.rs` are recognised as the same file.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                }
+                _ => out.push(".."),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+use std::io;
+
+/// Everything that can make a run fail. Each variant maps to its own exit code so
+/// scripts can tell the cases apart; see `exit_code`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Bad command line arguments or options.
+    Usage(String),
+    /// An input path does not exist.
+    MissingPath(String),
+    /// A file could not be read (other than being binary).
+    Unreadable {
+        path: String,
+        source: io::Error,
+    },
+    /// The run finished but left something out: `--keep-going` skipped missing or
+    /// unreadable paths, or `unpack`/`apply` could not handle every file.
+    PartialFailure(String),
+    /// The output has more estimated tokens than `--max-tokens` allows.
+    BudgetExceeded {
+        tokens: usize,
+        budget: usize,
+    },
+    /// `--fail-on-secrets` found secrets in a file.
+    SecretsFound {
+        path: String,
+        kinds: String,
+    },
+    Io(io::Error),
+    /// Anything else, e.g. an archive that cannot be read.
+    Other(String),
+}
+
+impl Error {
+    /// The process exit code for this error:
+    ///
+    /// | code | meaning |
+    /// |------|---------|
+    /// | 1 | any other error |
+    /// | 2 | usage error |
+    /// | 3 | missing or unreadable path |
+    /// | 4 | partial failure (`--keep-going` skipped paths, or `unpack`/`apply` left some files untouched) |
+    /// | 5 | token budget exceeded |
+    /// | 6 | secrets found with `--fail-on-secrets` |
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Usage(_) => 2,
+            Error::MissingPath(_) | Error::Unreadable { .. } => 3,
+            Error::PartialFailure(_) => 4,
+            Error::BudgetExceeded { .. } => 5,
+            Error::SecretsFound { .. } => 6,
+            Error::Io(_) | Error::Other(_) => 1,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(msg) | Error::PartialFailure(msg) | Error::Other(msg) => {
+                write!(f, "{}", msg)
+            }
+            Error::MissingPath(path) => write!(f, "Path does not exist: {}", path),
+            Error::Unreadable { path, source } => write!(f, "Cannot read {}: {}", path, source),
+            Error::BudgetExceeded { tokens, budget } => write!(
+                f,
+                "Output is ~{} tokens, over the budget of {}",
+                tokens, budget
+            ),
+            Error::SecretsFound { path, kinds } => {
+                write!(f, "Secrets found in {}: {}", path, kinds)
+            }
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Unreadable { source, .. } => Some(source),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Self {
+        Error::Other(msg)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(msg: &str) -> Self {
+        Error::Other(msg.to_string())
+    }
+}
+
+// Errors that went through a `Box<dyn Error>` (e.g. an archive callback) keep their
+// variant if they started out as one of ours.
+impl From<Box<dyn std::error::Error>> for Error {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        match e.downcast::<Error>() {
+            Ok(e) => *e,
+            Err(e) => Error::Other(e.to_string()),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
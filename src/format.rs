@@ -0,0 +1,277 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::process::GitignoreRule;
+use crate::stats::estimate_tokens;
+use crate::template::{FileVars, Template};
+
+/// A file that made it through selection and is ready to be printed.
+#[derive(Clone, Copy, Debug)]
+pub struct Document<'a> {
+    /// 1-based position of this document in the output.
+    pub index: usize,
+    /// The path as it should be shown (see `PathDisplay`).
+    pub path: &'a str,
+    pub content: &'a str,
+    /// Markdown code fence language, if we recognise the file type.
+    pub lang: Option<&'a str>,
+    /// 1-based, inclusive line range when only part of the file was asked for
+    /// (`path:120-180` or `path#symbol`).
+    pub lines: Option<(usize, usize)>,
+    /// `--meta` fields as `(name, value)` pairs; empty unless asked for.
+    pub meta: &'a [(&'a str, String)],
+}
+
+impl Document<'_> {
+    /// The path with the line range appended, e.g. `src/lib.rs:10-20`.
+    pub fn path_with_lines(&self) -> String {
+        match self.lines {
+            Some((start, end)) => format!("{}:{}-{}", self.path, start, end),
+            None => self.path.to_string(),
+        }
+    }
+}
+
+/// Why a file (or a whole directory) was left out of the output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SkipReason {
+    /// The name starts with `.` and `--include-hidden` was not given.
+    Hidden,
+    /// Matched this `.gitignore` rule.
+    Gitignore(GitignoreRule),
+    /// Matched this `--ignore` pattern.
+    IgnorePattern(String),
+    /// Not one of the `-e` extensions.
+    Extension,
+    /// Matched this `--exclude-ext` extension.
+    ExcludedExtension(String),
+    /// A generated, vendored or lock file; holds the pattern or header marker that matched.
+    Generated(String),
+    /// The content did not pass `--grep` / `--grep-invert`.
+    Grep,
+    /// Left out of a `--query` selection; holds why (no match, `--top`, `--budget`).
+    Cutoff(String),
+    /// The file is not valid UTF-8.
+    Binary,
+    /// Larger than `--max-size`; holds the file size in bytes.
+    TooLarge(u64),
+    /// The file could not be read; holds the I/O error message.
+    Unreadable(String),
+    /// An input path that does not exist (only with `--keep-going`).
+    Missing,
+}
+
+impl SkipReason {
+    /// Short machine-readable name, used for grouping and JSON output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SkipReason::Hidden => "hidden",
+            SkipReason::Gitignore(_) => "gitignore",
+            SkipReason::IgnorePattern(_) => "ignore_pattern",
+            SkipReason::Extension => "extension",
+            SkipReason::ExcludedExtension(_) => "excluded_extension",
+            SkipReason::Generated(_) => "generated",
+            SkipReason::Grep => "grep",
+            SkipReason::Cutoff(_) => "cutoff",
+            SkipReason::Binary => "binary",
+            SkipReason::TooLarge(_) => "too_large",
+            SkipReason::Unreadable(_) => "unreadable",
+            SkipReason::Missing => "missing",
+        }
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Hidden => write!(f, "hidden"),
+            SkipReason::Gitignore(rule) => write!(f, "gitignore rule {}", rule),
+            SkipReason::IgnorePattern(pat) => write!(f, "ignore pattern {:?}", pat),
+            SkipReason::Extension => write!(f, "extension not selected"),
+            SkipReason::ExcludedExtension(ext) => write!(f, "excluded extension {:?}", ext),
+            SkipReason::Generated(why) => write!(f, "generated ({})", why),
+            SkipReason::Grep => write!(f, "filtered out by --grep"),
+            SkipReason::Cutoff(why) => write!(f, "{}", why),
+            SkipReason::Binary => write!(f, "binary"),
+            SkipReason::TooLarge(size) => write!(f, "too large ({} bytes)", size),
+            SkipReason::Unreadable(err) => write!(f, "unreadable: {}", err),
+            SkipReason::Missing => write!(f, "does not exist"),
+        }
+    }
+}
+
+/// Decides how documents are written out.
+///
+/// `process_files` calls `prefix` for each `--prefix` text, `begin` once, then
+/// `file` or `skipped_file` for every candidate file in order, then `end` once and
+/// `suffix` for each `--suffix` text. Only `file` is required. Pruned directories are
+/// passed to `skipped_file` with a trailing `/`.
+pub trait Formatter {
+    /// Instructions that go before the documents, separated by a blank line.
+    fn prefix(&mut self, writer: &mut dyn Write, text: &str) -> io::Result<()> {
+        writeln!(writer, "{}\n", text.trim_end())
+    }
+
+    fn begin(&mut self, _writer: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn file(&mut self, writer: &mut dyn Write, doc: &Document) -> io::Result<()>;
+
+    fn skipped_file(
+        &mut self,
+        _writer: &mut dyn Write,
+        _path: &str,
+        _reason: &SkipReason,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn end(&mut self, _writer: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Instructions that go after the documents, separated by a blank line.
+    fn suffix(&mut self, writer: &mut dyn Write, text: &str) -> io::Result<()> {
+        writeln!(writer, "\n{}", text.trim_end())
+    }
+}
+
+/// The plain format: path, then the content between `---` lines. With `--meta` a
+/// line like `[bytes=120 git_author="Jane Doe"]` goes under the path.
+#[derive(Default)]
+pub struct DefaultFormatter;
+
+impl Formatter for DefaultFormatter {
+    fn file(&mut self, writer: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        writeln!(writer, "{}", doc.path_with_lines())?;
+        if !doc.meta.is_empty() {
+            let fields: Vec<String> = doc
+                .meta
+                .iter()
+                .map(|(name, value)| {
+                    if value.contains(|c: char| c.is_whitespace() || "\"]".contains(c)) {
+                        format!("{}={:?}", name, value)
+                    } else {
+                        format!("{}={}", name, value)
+                    }
+                })
+                .collect();
+            writeln!(writer, "[{}]", fields.join(" "))?;
+        }
+        writeln!(writer, "---")?;
+        writeln!(writer, "{}", doc.content)?;
+        writeln!(writer, "---")?;
+        Ok(())
+    }
+}
+
+/// XML-ish format suitable for Claude's long context window. `--meta` fields become
+/// attributes of `<document>`, and `--prefix`/`--suffix` texts go in `<prefix>` and
+/// `<suffix>` elements outside `<documents>`.
+#[derive(Default)]
+pub struct XmlFormatter;
+
+impl Formatter for XmlFormatter {
+    fn prefix(&mut self, writer: &mut dyn Write, text: &str) -> io::Result<()> {
+        writeln!(writer, "<prefix>\n{}\n</prefix>", text.trim_end())
+    }
+
+    fn begin(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        writeln!(writer, "<documents>")
+    }
+
+    fn file(&mut self, writer: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        let mut attributes = format!("index=\"{}\"", doc.index);
+        if let Some((start, end)) = doc.lines {
+            attributes.push_str(&format!(" lines=\"{}-{}\"", start, end));
+        }
+        for (name, value) in doc.meta {
+            attributes.push_str(&format!(" {}=\"{}\"", name, escape_attribute(value)));
+        }
+        writeln!(writer, "<document {}>", attributes)?;
+        writeln!(writer, "<source>{}</source>", doc.path)?;
+        writeln!(writer, "<document_content>")?;
+        writeln!(writer, "{}", doc.content)?;
+        writeln!(writer, "</document_content>")?;
+        writeln!(writer, "</document>")?;
+        Ok(())
+    }
+
+    fn end(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        writeln!(writer, "</documents>")
+    }
+
+    fn suffix(&mut self, writer: &mut dyn Write, text: &str) -> io::Result<()> {
+        writeln!(writer, "<suffix>\n{}\n</suffix>", text.trim_end())
+    }
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// `--list`: one path per line (or NUL terminated), optionally preceded by the
+/// size in bytes and estimated tokens.
+#[derive(Default)]
+pub struct ListFormatter {
+    pub null: bool,
+    pub sizes: bool,
+}
+
+impl Formatter for ListFormatter {
+    fn file(&mut self, writer: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        if self.sizes {
+            write!(
+                writer,
+                "{}\t{}\t",
+                doc.content.len(),
+                estimate_tokens(doc.content)
+            )?;
+        }
+        write!(writer, "{}", doc.path_with_lines())?;
+        writer.write_all(if self.null { b"\0" } else { b"\n" })
+    }
+}
+
+/// Renders documents through a user supplied `--template`.
+pub struct TemplateFormatter {
+    template: Template,
+    count: usize,
+}
+
+impl TemplateFormatter {
+    pub fn new(template: Template) -> TemplateFormatter {
+        TemplateFormatter { template, count: 0 }
+    }
+}
+
+impl Formatter for TemplateFormatter {
+    fn begin(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        write!(writer, "{}", self.template.render_header())
+    }
+
+    fn file(&mut self, writer: &mut dyn Write, doc: &Document) -> io::Result<()> {
+        let vars = FileVars {
+            path: doc.path,
+            content: doc.content,
+            index: doc.index,
+            lang: doc.lang.unwrap_or(""),
+            range: doc
+                .lines
+                .map(|(start, end)| format!("{}-{}", start, end))
+                .unwrap_or_default(),
+        };
+        self.count += 1;
+        write!(writer, "{}", self.template.render_file(&vars))
+    }
+
+    fn end(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        write!(writer, "{}", self.template.render_footer(self.count))
+    }
+}
@@ -0,0 +1,135 @@
+use glob::Pattern;
+
+// File names that are produced by tools rather than written by hand: lock files,
+// minified bundles, source maps and protobuf/codegen output.
+const GENERATED_FILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "composer.lock",
+    "Gemfile.lock",
+    "go.sum",
+    "flake.lock",
+    "*.min.js",
+    "*.min.css",
+    "*.js.map",
+    "*.css.map",
+    "*.pb.go",
+    "*.pb.cc",
+    "*.pb.h",
+    "*_pb2.py",
+    "*_pb2_grpc.py",
+    "*.g.dart",
+    "*.generated.*",
+];
+
+// Directories holding third-party code.
+const VENDORED_DIRS: &[&str] = &["vendor", "node_modules", "third_party", "bower_components"];
+
+// Only the top of a file is searched for a marker.
+const HEADER_LINES: usize = 5;
+
+// How a comment line starts, in the languages that mark generated files. Markers are
+// only looked for in comments, so prose that mentions them is left alone.
+const COMMENT_PREFIXES: &[&str] = &["//", "/*", "*", "#", "--", "<!--", ";", "%"];
+
+/// Recognises generated, vendored and lock files by name, and generated files by a
+/// marker comment near the top (`@generated`, Go's `Code generated ... DO NOT EDIT`).
+#[derive(Clone, Debug)]
+pub struct GeneratedFilter {
+    files: Vec<Pattern>,
+    dirs: Vec<Pattern>,
+    allowed: Vec<Pattern>,
+}
+
+impl Default for GeneratedFilter {
+    fn default() -> Self {
+        GeneratedFilter {
+            files: GENERATED_FILES
+                .iter()
+                .map(|s| Pattern::new(s).unwrap())
+                .collect(),
+            dirs: VENDORED_DIRS
+                .iter()
+                .map(|s| Pattern::new(s).unwrap())
+                .collect(),
+            allowed: Vec::new(),
+        }
+    }
+}
+
+impl GeneratedFilter {
+    /// Treat files matching this name glob as generated too. A trailing `/` makes it
+    /// a directory name.
+    pub fn add(&mut self, pattern: &str) -> Result<(), String> {
+        let (name, list) = match pattern.strip_suffix('/') {
+            Some(dir) => (dir, &mut self.dirs),
+            None => (pattern, &mut self.files),
+        };
+        let pat =
+            Pattern::new(name).map_err(|e| format!("Invalid pattern {:?}: {}", pattern, e))?;
+        list.push(pat);
+        Ok(())
+    }
+
+    /// Never treat files or directories matching this name glob as generated.
+    pub fn allow(&mut self, pattern: &str) -> Result<(), String> {
+        let pat = Pattern::new(pattern.trim_end_matches('/'))
+            .map_err(|e| format!("Invalid pattern {:?}: {}", pattern, e))?;
+        self.allowed.push(pat);
+        Ok(())
+    }
+
+    /// The pattern a file name matches, if any.
+    pub fn matching_file(&self, name: &str) -> Option<&str> {
+        if self.is_allowed(name) {
+            return None;
+        }
+        self.files
+            .iter()
+            .find(|p| p.matches(name))
+            .map(Pattern::as_str)
+    }
+
+    /// The pattern a directory name matches, if any.
+    pub fn matching_dir(&self, name: &str) -> Option<&str> {
+        if self.is_allowed(name) {
+            return None;
+        }
+        self.dirs
+            .iter()
+            .find(|p| p.matches(name))
+            .map(Pattern::as_str)
+    }
+
+    /// The marker found in a comment in the first few lines of `content`, if any.
+    pub fn header_marker(&self, name: &str, content: &str) -> Option<&'static str> {
+        if self.is_allowed(name) {
+            return None;
+        }
+        content.lines().take(HEADER_LINES).find_map(|line| {
+            let line = line.trim();
+            if !COMMENT_PREFIXES.iter().any(|p| line.starts_with(p)) {
+                None
+            } else if line.contains("@generated") {
+                Some("@generated")
+            } else if line.contains("DO NOT EDIT") {
+                Some("DO NOT EDIT")
+            } else {
+                let lower = line.to_lowercase();
+                (lower.contains("auto-generated") || lower.contains("autogenerated"))
+                    .then_some("auto-generated")
+            }
+        })
+    }
+
+    fn is_allowed(&self, name: &str) -> bool {
+        self.allowed.iter().any(|p| p.matches(name))
+    }
+}
@@ -0,0 +1,93 @@
+use regex::{Regex, RegexBuilder};
+
+/// Keeps only files whose content matches one of a set of regexes, and can cut a
+/// file down to the matching regions. Patterns run over the whole file, with `^` and
+/// `$` matching at line boundaries like grep, so a match may span lines.
+#[derive(Clone, Debug)]
+pub struct Grep {
+    patterns: Vec<Regex>,
+    /// Keep files that match none of the patterns instead.
+    pub invert: bool,
+    /// Emit only matching lines with this many lines of context around them.
+    pub context: Option<usize>,
+}
+
+impl Grep {
+    pub fn new(patterns: &[String]) -> Result<Grep, String> {
+        let patterns = patterns
+            .iter()
+            .map(|p| {
+                RegexBuilder::new(p)
+                    .multi_line(true)
+                    .build()
+                    .map_err(|e| format!("Invalid --grep pattern {:?}: {}", p, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Grep {
+            patterns,
+            invert: false,
+            context: None,
+        })
+    }
+
+    /// Should a file with this content be kept?
+    pub fn keeps(&self, content: &str) -> bool {
+        self.patterns.iter().any(|re| re.is_match(content)) != self.invert
+    }
+
+    /// The regions of `content` around matches, each headed by its 1-based line
+    /// range, e.g. `[lines 10-16]`. Overlapping regions are merged. Returns None when
+    /// no `context` was asked for.
+    pub fn excerpt(&self, content: &str) -> Option<String> {
+        let context = self.context?;
+        let lines: Vec<&str> = content.lines().collect();
+        if lines.is_empty() {
+            return Some(String::new());
+        }
+
+        // The line a byte offset falls on
+        let starts: Vec<usize> = content
+            .split_inclusive('\n')
+            .scan(0, |pos, line| {
+                let start = *pos;
+                *pos += line.len();
+                Some(start)
+            })
+            .collect();
+        let line_of = |offset: usize| starts.partition_point(|&s| s <= offset).saturating_sub(1);
+
+        // First and last line of every match
+        let mut matched: Vec<(usize, usize)> = self
+            .patterns
+            .iter()
+            .flat_map(|re| re.find_iter(content))
+            .map(|m| (line_of(m.start()), line_of(m.end().max(m.start() + 1) - 1)))
+            .collect();
+        matched.sort_unstable();
+
+        let mut regions: Vec<(usize, usize)> = Vec::new();
+        for (first, last) in matched {
+            let start = first.saturating_sub(context);
+            let end = last.saturating_add(context).min(lines.len() - 1);
+            match regions.last_mut() {
+                Some(prev) if start <= prev.1 + 1 => prev.1 = prev.1.max(end),
+                _ => regions.push((start, end)),
+            }
+        }
+
+        Some(
+            regions
+                .iter()
+                .map(|&(start, end)| {
+                    format!(
+                        "[lines {}-{}]\n{}",
+                        start + 1,
+                        end + 1,
+                        lines[start..=end].join("\n")
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        )
+    }
+}
@@ -0,0 +1,44 @@
+use std::path::Path;
+
+// Map a file to a language name as used in markdown code fences.
+// Returns None when we don't recognise the file.
+pub fn language_for(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_string_lossy();
+    match name.as_ref() {
+        "Makefile" | "makefile" | "GNUmakefile" => return Some("makefile"),
+        "Dockerfile" => return Some("dockerfile"),
+        "CMakeLists.txt" => return Some("cmake"),
+        _ => {}
+    }
+
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    let lang = match ext.as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" | "jsx" => "javascript",
+        "ts" | "mts" | "cts" | "tsx" => "typescript",
+        "go" => "go",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => "cpp",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "rb" => "ruby",
+        "php" => "php",
+        "cs" => "csharp",
+        "sh" | "bash" | "zsh" => "bash",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "json" => "json",
+        "md" | "markdown" => "markdown",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "sql" => "sql",
+        "xml" => "xml",
+        "nix" => "nix",
+        "lua" => "lua",
+        _ => return None,
+    };
+    Some(lang)
+}
@@ -0,0 +1,29 @@
+//! Concatenate a directory of files into a single prompt for LLMs.
+//!
+//! The `files-to-prompt` binary is a thin wrapper around [`process::process_files`].
+//! Library users can plug in their own output format by implementing
+//! [`format::Formatter`].
+
+pub mod apply;
+pub mod archive;
+pub mod cache;
+pub mod deps;
+pub mod error;
+pub mod format;
+pub mod generated;
+pub mod grep;
+pub mod lang;
+pub mod meta;
+pub mod notebook;
+pub mod outline;
+pub mod paths;
+pub mod process;
+pub mod rank;
+pub mod redact;
+pub mod slice;
+pub mod stats;
+pub mod template;
+pub mod transform;
+pub mod types;
+pub mod unpack;
+pub mod watch;
@@ -1,95 +1,761 @@
-use clap::{Arg, ArgAction, Command};
-use std::error::Error;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-mod process;
-use crate::process::process_files;
+use files_to_prompt::apply::{apply_edits, parse_edits};
+use files_to_prompt::cache::DiskCache;
+use files_to_prompt::deps::follow_deps;
+use files_to_prompt::error::{Error, Result};
+use files_to_prompt::format::{
+    DefaultFormatter, Formatter, ListFormatter, TemplateFormatter, XmlFormatter,
+};
+use files_to_prompt::generated::GeneratedFilter;
+use files_to_prompt::grep::Grep;
+use files_to_prompt::meta::MetaFields;
+use files_to_prompt::outline::Outliner;
+use files_to_prompt::paths::{PathDisplay, PathMode};
+use files_to_prompt::process::{explain, process_files, Options, Verdict};
+use files_to_prompt::rank::Ranking;
+use files_to_prompt::redact::Redactor;
+use files_to_prompt::slice::parse_path_arg;
+use files_to_prompt::template::Template;
+use files_to_prompt::types::FileTypes;
+use files_to_prompt::unpack::{parse_prompt, unpack_files, UnpackAction};
+use files_to_prompt::watch::{ContentCache, InputWatcher};
+use std::fs;
+use std::io::{self, Read};
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<()> {
     let matches = Command::new("files-to-prompt")
         .version("0.1.0")
         .about("Concatenate a directory of files into a single prompt for LLMs")
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            Command::new("unpack")
+                .about("Write the files contained in a prompt (default, --cxml or markdown format) back to disk")
+                .arg(
+                    Arg::new("input")
+                        .help("Prompt file to read, or - for stdin")
+                        .default_value("-")
+                )
+                .arg(
+                    Arg::new("into")
+                        .long("into")
+                        .help("Directory to write files under")
+                        .default_value(".")
+                        .value_name("DIR")
+                )
+                .arg(
+                    Arg::new("dry_run")
+                        .long("dry-run")
+                        .help("Only report what would be written")
+                        .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Overwrite files that already exist")
+                        .action(ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("apply")
+                .about("Apply unified diffs or SEARCH/REPLACE blocks from a model response to the files")
+                .arg(
+                    Arg::new("input")
+                        .help("Response file to read, or - for stdin")
+                        .default_value("-")
+                )
+                .arg(
+                    Arg::new("root")
+                        .long("root")
+                        .help("Input path the prompt was built from; edits outside every root are refused")
+                        .action(ArgAction::Append)
+                        .default_value(".")
+                        .value_name("PATH")
+                )
+                .arg(
+                    Arg::new("dry_run")
+                        .long("dry-run")
+                        .help("Check that every edit applies without writing anything")
+                        .action(ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("explain")
+                .about("Report which rule includes or excludes a path")
+                .arg(
+                    Arg::new("path")
+                        .help("File or directory to explain")
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("root")
+                        .long("root")
+                        .help("Input path the walk would start from")
+                        .default_value(".")
+                        .value_name("PATH")
+                )
+                .args(selection_args())
+        )
+        .subcommand(
+            Command::new("cache")
+                .about("Manage the cache of transformed content and token counts")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("clear")
+                        .about("Delete the cached entries (other files in the directory are left alone)")
+                )
+        )
         .arg(
             Arg::new("paths")
-                .help("Paths to files or directories")
-                .required(true)
+                .help("Paths to files or directories; file.rs:120-180 or file.rs#name selects part of a file")
+                .required_unless_present("type_list")
                 .num_args(1..)
         )
+        .args(selection_args())
+        .arg(
+            Arg::new("output_file")
+                .short('o')
+                .long("output")
+                .help("Output to a file instead of stdout")
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::new("cxml")
+                .short('c')
+                .long("cxml")
+                .help("Output in XML-ish format suitable for Claude’s long context window")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("path_mode")
+                .long("paths")
+                .help("How to show file paths: relative (to the repository root or current directory), absolute, or as-given")
+                .value_parser(["relative", "absolute", "as-given"])
+                .default_value("as-given")
+                .value_name("MODE")
+        )
+        .arg(
+            Arg::new("strip_prefix")
+                .long("strip-prefix")
+                .help("Remove this directory from the start of file paths")
+                .value_name("DIR")
+        )
+        .arg(
+            Arg::new("template")
+                .long("template")
+                .help("Format each file using a template with {{path}}, {{content}}, {{index}}, {{lang}}, {{lines}} and {{range}}, plus optional {{#header}}/{{#file}}/{{#footer}} sections")
+                .value_name("FILE")
+                .conflicts_with("cxml")
+        )
+        .arg(
+            Arg::new("list")
+                .long("list")
+                .help("Only print the paths of the files that would be included")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["cxml", "template"])
+        )
+        .arg(
+            Arg::new("null")
+                .short('0')
+                .long("null")
+                .help("With --list, end each path with a NUL byte instead of a newline")
+                .action(ArgAction::SetTrue)
+                .requires("list")
+        )
+        .arg(
+            Arg::new("sizes")
+                .long("sizes")
+                .help("With --list, also print each file's size in bytes and estimated tokens")
+                .action(ArgAction::SetTrue)
+                .requires("list")
+        )
         .arg(
-            Arg::new("extension")
-                .short('e')
-                .long("extension")
-                .help("Only include files with these extensions")
+            Arg::new("meta")
+                .long("meta")
+                .help("Attach metadata to each document: size, lines, modified, git (last commit author and date), hash, or all")
+                .value_name("FIELDS")
+                .value_delimiter(',')
                 .action(ArgAction::Append)
-                .value_name("EXT")
+                .conflicts_with_all(["list", "template"])
         )
         .arg(
-            Arg::new("include_hidden")
-                .long("include-hidden")
-                .help("Include files and folders starting with .")
+            Arg::new("prefix")
+                .long("prefix")
+                .help("Put instructions before the documents: the contents of FILE, or TEXT itself (repeatable)")
+                .value_name("FILE|TEXT")
+                .action(ArgAction::Append)
+                .conflicts_with("list")
+        )
+        .arg(
+            Arg::new("suffix")
+                .long("suffix")
+                .help("Put instructions after the documents: the contents of FILE, or TEXT itself (repeatable)")
+                .value_name("FILE|TEXT")
+                .action(ArgAction::Append)
+                .conflicts_with("list")
+        )
+        .arg(
+            Arg::new("strip_comments")
+                .long("strip-comments")
+                .help("Remove comments from source files (Rust, Python, JS/TS, Go, C/C++, shell, TOML, YAML and more)")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("compact")
+                .long("compact")
+                .help("Trim trailing whitespace and collapse runs of blank lines")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("notebook_outputs")
+                .long("notebook-outputs")
+                .help("Include cell outputs when rendering Jupyter notebooks (images become placeholders)")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("outline")
+                .long("outline")
+                .help("Only show signatures and type definitions of source files (Rust and Python), eliding function bodies")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("redact")
+                .long("redact")
+                .help("Replace secrets (API keys, private keys, JWTs, passwords, high-entropy strings) with [REDACTED:kind]")
                 .action(ArgAction::SetTrue)
         )
         .arg(
-            Arg::new("ignore_gitignore")
-                .long("ignore-gitignore")
-                .help("Ignore .gitignore files and include all files")
+            Arg::new("redact_config")
+                .long("redact-config")
+                .help("Extra secret patterns, one `kind = regex` per line (implies --redact)")
+                .action(ArgAction::Append)
+                .value_name("FILE")
+        )
+        .arg(
+            Arg::new("fail_on_secrets")
+                .long("fail-on-secrets")
+                .help("Exit with an error instead of redacting when a secret is found")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .help("Print a summary of included and skipped files to stderr")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("stats_json")
+                .long("stats-json")
+                .help("Print the summary as JSON to stderr")
                 .action(ArgAction::SetTrue)
         )
         .arg(
-            Arg::new("ignore_patterns")
-                .long("ignore")
-                .help("Ignore files matching these patterns")
+            Arg::new("stats_top")
+                .long("stats-top")
+                .help("Number of largest files to list in the summary")
+                .value_parser(value_parser!(usize))
+                .default_value("10")
+                .value_name("N")
+        )
+        .arg(
+            Arg::new("grep")
+                .long("grep")
+                .help("Only include files whose content matches this regex (repeatable; any match counts)")
                 .action(ArgAction::Append)
                 .value_name("PATTERN")
         )
         .arg(
-            Arg::new("output_file")
-                .short('o')
-                .long("output")
-                .help("Output to a file instead of stdout")
-                .value_name("FILE")
+            Arg::new("grep_invert")
+                .long("grep-invert")
+                .help("With --grep, only include files that don't match")
+                .action(ArgAction::SetTrue)
+                .requires("grep")
         )
         .arg(
-            Arg::new("cxml")
-                .short('c')
-                .long("cxml")
-                .help("Output in XML-ish format suitable for Claude’s long context window")
+            Arg::new("context")
+                .long("context")
+                .help("With --grep, emit only the matching lines plus N lines around them, headed by their line ranges")
+                .value_parser(value_parser!(usize))
+                .value_name("N")
+                .requires("grep")
+                .conflicts_with_all(["grep_invert", "outline"])
+        )
+        .arg(
+            Arg::new("query")
+                .long("query")
+                .help("Only include files relevant to this query, best match first (BM25 over contents and paths)")
+                .value_name("TEXT")
+        )
+        .arg(
+            Arg::new("top")
+                .long("top")
+                .help("With --query, include at most K files")
+                .value_parser(value_parser!(usize))
+                .value_name("K")
+                .requires("query")
+        )
+        .arg(
+            Arg::new("budget")
+                .long("budget")
+                .help("With --query, include files in rank order while they fit in this many estimated tokens")
+                .value_parser(value_parser!(usize))
+                .value_name("TOKENS")
+                .requires("query")
+        )
+        .arg(
+            Arg::new("follow_deps")
+                .long("follow-deps")
+                .help("Treat file paths as entry points and also include the files they depend on (Rust mod and crate:: paths, relative Python and JS/TS imports)")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("deps_depth")
+                .long("deps-depth")
+                .help("With --follow-deps, follow dependencies at most N levels deep")
+                .value_parser(value_parser!(usize))
+                .value_name("N")
+                .requires("follow_deps")
+        )
+        .arg(
+            Arg::new("type_list")
+                .long("type-list")
+                .help("Print the file types available to --type and exit")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("max_tokens")
+                .long("max-tokens")
+                .help("Exit with status 5 if the output is estimated at more than N tokens")
+                .value_parser(value_parser!(usize))
+                .value_name("N")
+        )
+        .arg(
+            Arg::new("keep_going")
+                .long("keep-going")
+                .help("Warn about missing or unreadable paths and continue; exits with status 4 if any were skipped")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .help("Keep running and rewrite the -o output file whenever an input file changes")
+                .action(ArgAction::SetTrue)
+                .requires("output_file")
+        )
+        .arg(
+            Arg::new("no_cache")
+                .long("no-cache")
+                .help("Don't read or write the on-disk cache of transformed content (kept in $FILES_TO_PROMPT_CACHE_DIR or ~/.cache/files-to-prompt)")
                 .action(ArgAction::SetTrue)
         )
+        .after_help("Exit status: 0 success, 1 other error, 2 usage error, 3 missing or unreadable path, 4 partial failure, 5 token budget exceeded, 6 secrets found")
         .get_matches();
 
-    let paths: Vec<String> = matches
+    match matches.subcommand() {
+        Some(("unpack", sub)) => return run_unpack(sub),
+        Some(("apply", sub)) => return run_apply(sub),
+        Some(("explain", sub)) => return run_explain(sub),
+        Some(("cache", sub)) => return run_cache(sub),
+        _ => {}
+    }
+
+    if matches.get_flag("type_list") {
+        print!("{}", file_types(&matches)?.list());
+        return Ok(());
+    }
+
+    let entries: Vec<String> = matches
         .get_many::<String>("paths")
         .unwrap()
         .map(|s| s.to_string())
         .collect();
+    // Worked out again on every rebuild with --watch, as edits can change the imports
+    let selected_paths = || {
+        if matches.get_flag("follow_deps") {
+            follow_deps(&entries, matches.get_one::<usize>("deps_depth").copied())
+        } else {
+            entries.clone()
+        }
+    };
 
-    let extensions: Vec<String> = matches
-        .get_many::<String>("extension")
+    let output_file = matches.get_one::<String>("output_file").map(ToString::to_string);
+    let claude_xml = matches.get_flag("cxml");
+
+    let path_mode = PathMode::parse(matches.get_one::<String>("path_mode").unwrap()).unwrap();
+    let strip_prefix = matches.get_one::<String>("strip_prefix");
+    let path_display = PathDisplay::new(path_mode, strip_prefix.map(String::as_str));
+
+    let template = matches.get_one::<String>("template");
+    let mut formatter: Box<dyn Formatter> = if matches.get_flag("list") {
+        Box::new(ListFormatter {
+            null: matches.get_flag("null"),
+            sizes: matches.get_flag("sizes"),
+        })
+    } else if let Some(t) = template {
+        let template =
+            Template::from_file(Path::new(t)).map_err(|e| Error::Usage(e.to_string()))?;
+        Box::new(TemplateFormatter::new(template))
+    } else if claude_xml {
+        Box::new(XmlFormatter)
+    } else {
+        Box::new(DefaultFormatter)
+    };
+
+    let fail_on_secrets = matches.get_flag("fail_on_secrets");
+    let redact_configs: Vec<&String> = matches
+        .get_many::<String>("redact_config")
         .unwrap_or_default()
-        .map(|s| s.to_string())
         .collect();
+    let redactor = if matches.get_flag("redact") || fail_on_secrets || !redact_configs.is_empty() {
+        let mut redactor = Redactor::default();
+        for config in redact_configs {
+            redactor
+                .add_config(Path::new(config))
+                .map_err(|e| Error::Usage(e.to_string()))?;
+        }
+        Some(redactor)
+    } else {
+        None
+    };
 
-    let include_hidden = matches.get_flag("include_hidden");
-    let ignore_gitignore = matches.get_flag("ignore_gitignore");
-    let ignore_patterns: Vec<String> = matches
-        .get_many::<String>("ignore_patterns")
+    let grep = match matches.get_many::<String>("grep") {
+        Some(patterns) => {
+            let patterns: Vec<String> = patterns.cloned().collect();
+            let mut grep = Grep::new(&patterns).map_err(Error::Usage)?;
+            grep.invert = matches.get_flag("grep_invert");
+            grep.context = matches.get_one::<usize>("context").copied();
+            Some(grep)
+        }
+        None => None,
+    };
+
+    let meta: Vec<&String> = matches
+        .get_many::<String>("meta")
         .unwrap_or_default()
-        .map(|s| s.to_string())
         .collect();
 
-    let output_file = matches.get_one::<String>("output_file").map(ToString::to_string);
-    let claude_xml = matches.get_flag("cxml");
+    let opts = Options {
+        output_file,
+        grep,
+        path_display,
+        strip_comments: matches.get_flag("strip_comments"),
+        compact: matches.get_flag("compact"),
+        notebook_outputs: matches.get_flag("notebook_outputs"),
+        outliner: matches.get_flag("outline").then(Outliner::default),
+        redactor,
+        fail_on_secrets,
+        keep_going: matches.get_flag("keep_going"),
+        ranking: matches.get_one::<String>("query").map(|q| Ranking {
+            top: matches.get_one::<usize>("top").copied(),
+            budget: matches.get_one::<usize>("budget").copied(),
+            ..Ranking::new(q)
+        }),
+        cache: matches.get_flag("watch").then(ContentCache::default),
+        meta: MetaFields::parse(&meta).map_err(Error::Usage)?,
+        prefix: instruction_texts(&matches, "prefix")?,
+        suffix: instruction_texts(&matches, "suffix")?,
+        disk_cache: if matches.get_flag("no_cache") {
+            None
+        } else {
+            DiskCache::default_dir().map(DiskCache::new)
+        },
+        ..selection_options(&matches)?
+    };
+
+    if let Some(cache) = &opts.cache {
+        let output = opts.output_file.as_deref().unwrap();
+        let mut watcher = InputWatcher::new(Path::new(output))?;
+        let mut first = true;
+        loop {
+            // Watch before reading, so no change made during the rebuild is missed
+            let paths = selected_paths();
+            let watched: Vec<String> = paths.iter().map(|p| parse_path_arg(p).0).collect();
+            watcher.watch_paths(&watched)?;
+            match process_files(&paths, &opts, formatter.as_mut()) {
+                Ok(stats) => eprintln!(
+                    "Wrote {} files (~{} tokens) to {}",
+                    stats.included.len(),
+                    stats.total_tokens(),
+                    output
+                ),
+                Err(e) if first => return Err(e),
+                Err(e) => eprintln!("Error: {}", e),
+            }
+            first = false;
+
+            let Some(changed) = watcher.next_changes() else {
+                return Ok(());
+            };
+            cache.invalidate(&changed);
+        }
+    }
+
+    let stats = process_files(&selected_paths(), &opts, formatter.as_mut())?;
+
+    let top = *matches.get_one::<usize>("stats_top").unwrap();
+    if matches.get_flag("stats_json") {
+        let json = serde_json::to_string_pretty(&stats.to_json(top)).map_err(|e| e.to_string())?;
+        eprintln!("{}", json);
+    } else if matches.get_flag("stats") {
+        eprint!("{}", stats.report(top));
+    }
+
+    if let Some(&budget) = matches.get_one::<usize>("max_tokens") {
+        let tokens = stats.total_tokens();
+        if tokens > budget {
+            return Err(Error::BudgetExceeded { tokens, budget });
+        }
+    }
+    // Without --keep-going, unreadable files found by the walk are only warned about
+    let failed = stats.failed();
+    if opts.keep_going && failed > 0 {
+        return Err(Error::PartialFailure(format!(
+            "{} path(s) could not be read",
+            failed
+        )));
+    }
+
+    Ok(())
+}
+
+// Arguments that decide which files are selected, shared by the main command and `explain`.
+fn selection_args() -> Vec<Arg> {
+    vec![
+        Arg::new("extension")
+            .short('e')
+            .long("extension")
+            .help("Only include files with these extensions (e.g. rs, d.ts, or a name like Makefile)")
+            .action(ArgAction::Append)
+            .value_name("EXT"),
+        Arg::new("exclude_extension")
+            .long("exclude-ext")
+            .help("Leave out files with these extensions")
+            .action(ArgAction::Append)
+            .value_name("EXT"),
+        Arg::new("case_sensitive_ext")
+            .long("case-sensitive-ext")
+            .help("Match -e and --exclude-ext case-sensitively")
+            .action(ArgAction::SetTrue),
+        Arg::new("include_generated")
+            .long("include-generated")
+            .help("Don't leave out generated, vendored and lock files (Cargo.lock, *.min.js, vendor/, @generated headers, ...)")
+            .action(ArgAction::SetTrue),
+        Arg::new("generated")
+            .long("generated")
+            .help("Also treat files matching this name glob as generated (a trailing / matches a directory)")
+            .action(ArgAction::Append)
+            .value_name("PATTERN"),
+        Arg::new("not_generated")
+            .long("not-generated")
+            .help("Never treat files or directories matching this name glob as generated")
+            .action(ArgAction::Append)
+            .value_name("PATTERN"),
+        Arg::new("type")
+            .short('t')
+            .long("type")
+            .help("Only include files of this type, e.g. rust, web, config, docs (see --type-list)")
+            .action(ArgAction::Append)
+            .value_name("TYPE"),
+        Arg::new("type_add")
+            .long("type-add")
+            .help("Define or extend a type, e.g. 'proto:*.proto'")
+            .action(ArgAction::Append)
+            .value_name("NAME:GLOB"),
+        Arg::new("include_hidden")
+            .long("include-hidden")
+            .help("Include files and folders starting with .")
+            .action(ArgAction::SetTrue),
+        Arg::new("ignore_gitignore")
+            .long("ignore-gitignore")
+            .help("Ignore .gitignore files and include all files")
+            .action(ArgAction::SetTrue),
+        Arg::new("ignore_patterns")
+            .long("ignore")
+            .help("Ignore files matching these patterns")
+            .action(ArgAction::Append)
+            .value_name("PATTERN"),
+        Arg::new("max_size")
+            .long("max-size")
+            .help("Skip files larger than this many bytes")
+            .value_parser(value_parser!(u64))
+            .value_name("BYTES"),
+    ]
+}
+
+fn selection_options(matches: &ArgMatches) -> Result<Options> {
+    let strings = |id: &str| -> Vec<String> {
+        matches
+            .get_many::<String>(id)
+            .unwrap_or_default()
+            .map(|s| s.to_string())
+            .collect()
+    };
+    let types = file_types(matches)?;
+    let mut extensions = strings("extension");
+    for name in strings("type") {
+        extensions.extend(types.extensions(&name).map_err(Error::Usage)?);
+    }
+    let generated = if matches.get_flag("include_generated") {
+        None
+    } else {
+        let mut filter = GeneratedFilter::default();
+        for pattern in strings("generated") {
+            filter.add(&pattern).map_err(Error::Usage)?;
+        }
+        for pattern in strings("not_generated") {
+            filter.allow(&pattern).map_err(Error::Usage)?;
+        }
+        Some(filter)
+    };
+    Ok(Options {
+        extensions,
+        generated,
+        exclude_extensions: strings("exclude_extension"),
+        case_sensitive_extensions: matches.get_flag("case_sensitive_ext"),
+        include_hidden: matches.get_flag("include_hidden"),
+        ignore_gitignore: matches.get_flag("ignore_gitignore"),
+        ignore_patterns: strings("ignore_patterns"),
+        max_size: matches.get_one::<u64>("max_size").copied(),
+        ..Default::default()
+    })
+}
+
+fn file_types(matches: &ArgMatches) -> Result<FileTypes> {
+    let mut types = FileTypes::default();
+    for spec in matches.get_many::<String>("type_add").unwrap_or_default() {
+        types.add(spec).map_err(Error::Usage)?;
+    }
+    Ok(types)
+}
 
-    process_files(
-        &paths,
-        &extensions,
-        include_hidden,
-        ignore_gitignore,
-        &ignore_patterns,
-        output_file.as_deref(),
-        claude_xml,
-    )?;
+// Read a subcommand's INPUT argument: a file, or stdin for "-".
+fn read_input(matches: &ArgMatches) -> Result<String> {
+    let input = matches.get_one::<String>("input").unwrap();
+    if input == "-" {
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf)?;
+        Ok(buf)
+    } else {
+        Ok(fs::read_to_string(input).map_err(|e| format!("Cannot read {}: {}", input, e))?)
+    }
+}
+
+fn run_unpack(matches: &ArgMatches) -> Result<()> {
+    let text = read_input(matches)?;
+    let files = parse_prompt(&text);
+    if files.is_empty() {
+        return Err("No files found in input".into());
+    }
+
+    let target = Path::new(matches.get_one::<String>("into").unwrap());
+    let dry_run = matches.get_flag("dry_run");
+    let report = unpack_files(&files, target, dry_run, matches.get_flag("force"))?;
+
+    let mut refused = 0;
+    for (path, action) in &report {
+        println!("{}{} {}", if dry_run { "would " } else { "" }, action.label(), path);
+        if matches!(action, UnpackAction::SkipExisting | UnpackAction::RejectUnsafe) {
+            refused += 1;
+        }
+    }
+    if refused > 0 {
+        return Err(Error::PartialFailure(format!(
+            "{} file(s) were not written",
+            refused
+        )));
+    }
+    Ok(())
+}
+
+fn run_apply(matches: &ArgMatches) -> Result<()> {
+    let text = read_input(matches)?;
+    let edits = parse_edits(&text);
+    if edits.is_empty() {
+        return Err("No diffs or SEARCH/REPLACE blocks found in input".into());
+    }
+
+    let roots: Vec<PathBuf> = matches
+        .get_many::<String>("root")
+        .unwrap_or_default()
+        .map(PathBuf::from)
+        .collect();
+    let dry_run = matches.get_flag("dry_run");
+    let reports = apply_edits(&edits, Path::new("."), &roots, dry_run)?;
 
+    let mut failed = 0;
+    for report in &reports {
+        if report.applied > 0 {
+            let verb = if dry_run { "would apply" } else { "applied" };
+            println!("{} {} edit(s) to {}", verb, report.applied, report.path);
+        }
+        for failure in &report.failures {
+            println!("failed {}: {}", report.path, failure);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(Error::PartialFailure(format!(
+            "{} edit(s) failed to apply",
+            failed
+        )));
+    }
     Ok(())
 }
 
+fn run_explain(matches: &ArgMatches) -> Result<()> {
+    let path = matches.get_one::<String>("path").unwrap();
+    let root = matches.get_one::<String>("root").unwrap();
+    let opts = selection_options(matches)?;
+    match explain(Path::new(path), Path::new(root), &opts)? {
+        Verdict::Included => println!("{}: included", path),
+        Verdict::Skipped(reason) => println!("{}: excluded, {}", path, reason),
+        Verdict::InSkippedDir(dir, reason) => println!(
+            "{}: excluded, directory {} is skipped: {}",
+            path,
+            dir.display(),
+            reason
+        ),
+    }
+    Ok(())
+}
+
+// `--prefix` and `--suffix` take either a file to read or the text itself.
+fn instruction_texts(matches: &ArgMatches, id: &str) -> Result<Vec<String>> {
+    matches
+        .get_many::<String>(id)
+        .unwrap_or_default()
+        .map(|value| {
+            if Path::new(value).is_file() {
+                fs::read_to_string(value).map_err(|e| Error::Unreadable {
+                    path: value.clone(),
+                    source: e,
+                })
+            } else {
+                Ok(value.clone())
+            }
+        })
+        .collect()
+}
+
+fn run_cache(matches: &ArgMatches) -> Result<()> {
+    let dir = DiskCache::default_dir()
+        .ok_or_else(|| Error::Usage("Can't find a cache directory: $HOME is not set".to_string()))?;
+    if let Some(("clear", _)) = matches.subcommand() {
+        let removed = DiskCache::new(&dir).clear()?;
+        println!("Removed {} cached entries from {}", removed, dir.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+/// The names `--meta` accepts, besides `all`.
+pub const META_FIELDS: &[&str] = &["size", "lines", "modified", "git", "hash"];
+
+/// Which metadata to attach to every document (`--meta`). Size, line count and hash
+/// describe the content as emitted; the modification time and last commit describe
+/// the file on disk, so archive members don't get them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetaFields {
+    pub size: bool,
+    pub lines: bool,
+    pub modified: bool,
+    /// Author and date of the last commit that touched the file.
+    pub git: bool,
+    pub hash: bool,
+}
+
+impl MetaFields {
+    /// Turn on the named fields; `all` turns on every one.
+    pub fn parse<S: AsRef<str>>(names: &[S]) -> Result<MetaFields, String> {
+        let mut fields = MetaFields::default();
+        for name in names {
+            match name.as_ref().trim() {
+                "size" => fields.size = true,
+                "lines" => fields.lines = true,
+                "modified" => fields.modified = true,
+                "git" => fields.git = true,
+                "hash" => fields.hash = true,
+                "all" => {
+                    fields = MetaFields {
+                        size: true,
+                        lines: true,
+                        modified: true,
+                        git: true,
+                        hash: true,
+                    }
+                }
+                other => {
+                    return Err(format!(
+                        "Unknown --meta field {:?} (expected {} or all)",
+                        other,
+                        META_FIELDS.join(", ")
+                    ))
+                }
+            }
+        }
+        Ok(fields)
+    }
+
+    /// `(name, value)` pairs for one document, always in the same order. `file` is
+    /// the file on disk the document was read from, if any.
+    pub fn collect(
+        &self,
+        file: Option<(&Path, &fs::Metadata)>,
+        content: &str,
+    ) -> Vec<(&'static str, String)> {
+        let mut meta = Vec::new();
+        if self.size {
+            meta.push(("bytes", content.len().to_string()));
+        }
+        if self.lines {
+            meta.push(("line_count", content.lines().count().to_string()));
+        }
+        if self.modified {
+            if let Some(modified) = file.and_then(|(_, m)| m.modified().ok()) {
+                meta.push(("modified", format_utc(modified)));
+            }
+        }
+        if self.git {
+            if let Some((author, date)) = file.and_then(|(path, _)| last_commit(path)) {
+                meta.push(("git_author", author));
+                meta.push(("git_date", date));
+            }
+        }
+        if self.hash {
+            let digest = Sha256::digest(content.as_bytes());
+            let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+            meta.push(("sha256", hex));
+        }
+        meta
+    }
+}
+
+// Author and ISO 8601 date of the last commit touching `path`, or None outside a
+// repository or for untracked files.
+fn last_commit(path: &Path) -> Option<(String, String)> {
+    let dir = path
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["log", "-1", "--format=%an%x00%aI", "--"])
+        .arg(path.file_name()?)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    let (author, date) = text.trim_end().split_once('\0')?;
+    Some((author.to_string(), date.to_string()))
+}
+
+// `2024-05-01T12:34:56Z`
+fn format_utc(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // Days since the epoch to a civil date, from Howard Hinnant's date algorithms
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
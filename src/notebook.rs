@@ -0,0 +1,91 @@
+use serde_json::Value;
+
+// Render a Jupyter notebook as ordered cells in the "percent" format understood by
+// Jupytext and most editors:
+//
+//     # %% [markdown]
+//     Some prose
+//
+//     # %%
+//     print("code")
+//
+//     # %% [output]
+//     code
+//
+// Metadata and execution counts are dropped. Outputs are only included when asked for,
+// and images or other binary outputs become a `[image/png output]` placeholder.
+pub fn render_notebook(json: &str, include_outputs: bool) -> Result<String, String> {
+    let nb: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let cells = nb
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or("notebook has no cells array")?;
+
+    let mut sections: Vec<String> = Vec::new();
+    for cell in cells {
+        let source = join_text(cell.get("source"));
+        match cell.get("cell_type").and_then(Value::as_str) {
+            Some("markdown") => sections.push(format!("# %% [markdown]\n{}", source)),
+            Some("code") => {
+                sections.push(format!("# %%\n{}", source));
+                if include_outputs {
+                    let outputs = render_outputs(cell.get("outputs"));
+                    if !outputs.is_empty() {
+                        sections.push(format!("# %% [output]\n{}", outputs));
+                    }
+                }
+            }
+            Some("raw") => sections.push(format!("# %% [raw]\n{}", source)),
+            _ => {}
+        }
+    }
+
+    Ok(sections
+        .iter()
+        .map(|s| s.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n\n"))
+}
+
+fn render_outputs(outputs: Option<&Value>) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for output in outputs.and_then(Value::as_array).into_iter().flatten() {
+        match output.get("output_type").and_then(Value::as_str) {
+            Some("stream") => parts.push(join_text(output.get("text"))),
+            Some("execute_result") | Some("display_data") => {
+                let Some(data) = output.get("data").and_then(Value::as_object) else {
+                    continue;
+                };
+                if let Some(text) = data.get("text/plain") {
+                    parts.push(join_text(Some(text)));
+                }
+                for mime in data.keys().filter(|m| m.as_str() != "text/plain") {
+                    if mime.starts_with("image/") {
+                        parts.push(format!("[{} output]", mime));
+                    }
+                }
+            }
+            Some("error") => {
+                let name = output.get("ename").and_then(Value::as_str).unwrap_or("Error");
+                let value = output.get("evalue").and_then(Value::as_str).unwrap_or("");
+                parts.push(format!("{}: {}", name, value));
+            }
+            _ => {}
+        }
+    }
+    parts
+        .iter()
+        .map(|p| p.trim_end())
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Notebook text fields are either a single string or a list of lines.
+fn join_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
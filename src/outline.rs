@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::transform::strip_comments;
+
+/// Reduces a source file to its structure: signatures and type definitions, with
+/// function bodies elided.
+pub trait OutlineExtractor {
+    fn outline(&self, content: &str) -> String;
+}
+
+/// Per-language registry of outline extractors, keyed by the language names
+/// returned by `language_for`.
+pub struct Outliner {
+    extractors: HashMap<String, Box<dyn OutlineExtractor>>,
+}
+
+impl Default for Outliner {
+    fn default() -> Self {
+        let mut outliner = Outliner {
+            extractors: HashMap::new(),
+        };
+        outliner.register("rust", Box::new(RustOutline));
+        outliner.register("python", Box::new(PythonOutline));
+        outliner
+    }
+}
+
+impl Outliner {
+    /// Add or replace the extractor used for `lang`.
+    pub fn register(&mut self, lang: &str, extractor: Box<dyn OutlineExtractor>) {
+        self.extractors.insert(lang.to_string(), extractor);
+    }
+
+    /// Outline `content`, or return None if there is no extractor for `lang`.
+    pub fn outline(&self, content: &str, lang: Option<&str>) -> Option<String> {
+        let extractor = self.extractors.get(lang?)?;
+        Some(extractor.outline(content))
+    }
+
+    /// The languages that have an extractor, sorted.
+    pub fn languages(&self) -> Vec<&str> {
+        let mut langs: Vec<&str> = self.extractors.keys().map(String::as_str).collect();
+        langs.sort();
+        langs
+    }
+}
+
+lazy_static! {
+    static ref RUST_ITEM: Regex = Regex::new(
+        r#"^\s*(?:pub(?:\s*\([^)]*\))?\s+)?(?:(?:default|const|async|unsafe|extern(?:\s+"[^"]*")?)\s+)*(fn|struct|enum|union|trait|impl|type|mod|const|static|macro_rules!)[\s<{(]"#
+    )
+    .unwrap();
+    static ref PYTHON_ITEM: Regex = Regex::new(r"^(\s*)(?:async\s+def|def|class)\s").unwrap();
+    static ref PYTHON_DECORATOR: Regex = Regex::new(r"^(\s*)@").unwrap();
+}
+
+/// Keeps `fn` signatures, type definitions with their fields, and `impl` / `trait` /
+/// `mod` blocks with their contents outlined in turn.
+pub struct RustOutline;
+
+impl OutlineExtractor for RustOutline {
+    fn outline(&self, content: &str) -> String {
+        let stripped = strip_comments(content, Some("rust"));
+        let lines: Vec<&str> = stripped.lines().collect();
+        let mut out: Vec<String> = Vec::new();
+        // Brace depths at which the kept impl/trait/mod blocks were opened.
+        let mut containers: Vec<i32> = Vec::new();
+        let mut depth = 0;
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i];
+            let at_item_level = containers.last().map_or(depth == 0, |&d| d == depth);
+            let kind = RUST_ITEM
+                .captures(line)
+                .filter(|_| at_item_level)
+                .map(|c| c[1].to_string());
+
+            let Some(kind) = kind else {
+                let before = depth;
+                depth += brace_delta(line);
+                if containers.last().is_some_and(|&d| depth < d && before >= d) {
+                    containers.pop();
+                    out.push(line.trim_end().to_string());
+                }
+                i += 1;
+                continue;
+            };
+
+            // Gather the signature up to its opening brace or terminating semicolon,
+            // or for constants up to the `=` that starts the value.
+            let is_value = kind == "const" || kind == "static";
+            let mut end = i;
+            while end + 1 < lines.len()
+                && (!is_value || !lines[end].contains('='))
+                && !lines[end].contains('{')
+                && !lines[end].trim_end().ends_with(';')
+            {
+                end += 1;
+            }
+            let header = &lines[i..=end];
+            let opens_block = lines[end].contains('{');
+
+            match kind.as_str() {
+                "fn" | "const" | "static" | "macro_rules!" if opens_block || kind != "fn" => {
+                    // Keep the signature, elide the body or value.
+                    let (sig, elided) = if kind == "fn" || kind == "macro_rules!" {
+                        (lines[end].split('{').next().unwrap(), " { ... }")
+                    } else {
+                        (lines[end].split('=').next().unwrap(), " = ...;")
+                    };
+                    out.extend(header[..header.len() - 1].iter().map(|l| l.to_string()));
+                    out.push(format!("{}{}", sig.trim_end(), elided));
+                    i = skip_item(&lines, i, &mut depth);
+                }
+                "impl" | "trait" | "mod" if opens_block => {
+                    out.extend(header.iter().map(|l| l.trim_end().to_string()));
+                    let before = depth;
+                    for l in header {
+                        depth += brace_delta(l);
+                    }
+                    if depth > before {
+                        containers.push(depth);
+                    }
+                    i = end + 1;
+                }
+                _ => {
+                    // Type definitions (and `fn` declarations without a body) are kept whole.
+                    let next = skip_item(&lines, i, &mut depth);
+                    out.extend(lines[i..next].iter().map(|l| l.trim_end().to_string()));
+                    i = next;
+                }
+            }
+        }
+
+        out.join("\n")
+    }
+}
+
+// Advance past the item starting at `start`: until its braces balance, or until the
+// `;` that ends it if it has no body. Returns the index of the next line.
+pub(crate) fn skip_item(lines: &[&str], start: usize, depth: &mut i32) -> usize {
+    let base = *depth;
+    let mut seen_brace = false;
+    let mut i = start;
+    while i < lines.len() {
+        let line = lines[i];
+        seen_brace |= line.contains('{');
+        *depth += brace_delta(line);
+        i += 1;
+        if *depth <= base && (seen_brace || line.trim_end().ends_with(';')) {
+            break;
+        }
+    }
+    *depth = base;
+    i
+}
+
+// Net change in `{`/`}` nesting on a line, ignoring braces in string and char literals.
+fn brace_delta(line: &str) -> i32 {
+    let chars: Vec<char> = line.chars().collect();
+    let mut delta = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '"' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            '\'' if chars.get(i + 2) == Some(&'\'') => i += 2,
+            '{' => delta += 1,
+            '}' => delta -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    delta
+}
+
+/// Keeps `class` and `def` lines (with decorators); function bodies become `...`.
+pub struct PythonOutline;
+
+impl OutlineExtractor for PythonOutline {
+    fn outline(&self, content: &str) -> String {
+        let stripped = strip_comments(content, Some("python"));
+        let lines: Vec<&str> = stripped.lines().collect();
+        let mut out: Vec<String> = Vec::new();
+        // Indent of the def whose body we're currently skipping.
+        let mut skipping: Option<usize> = None;
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i];
+            let indent = line.len() - line.trim_start().len();
+            if line.trim().is_empty() {
+                i += 1;
+                continue;
+            }
+            if let Some(def_indent) = skipping {
+                if indent > def_indent {
+                    i += 1;
+                    continue;
+                }
+                skipping = None;
+            }
+
+            if PYTHON_DECORATOR.is_match(line) {
+                out.push(line.trim_end().to_string());
+                i += 1;
+            } else if PYTHON_ITEM.is_match(line) {
+                // Signatures may span lines until the `:` outside any brackets.
+                let mut depth = 0;
+                let mut one_line_body = false;
+                loop {
+                    let l = lines[i];
+                    i += 1;
+                    match header_colon(l, &mut depth) {
+                        Some(colon) if !l[colon + 1..].trim().is_empty() => {
+                            out.push(format!("{}: ...", &l[..colon]));
+                            one_line_body = true;
+                            break;
+                        }
+                        Some(_) => {
+                            out.push(l.trim_end().to_string());
+                            break;
+                        }
+                        None => out.push(l.trim_end().to_string()),
+                    }
+                    if i >= lines.len() {
+                        break;
+                    }
+                }
+                if !one_line_body && !line.trim_start().starts_with("class") {
+                    out.push(format!("{}    ...", " ".repeat(indent)));
+                    skipping = Some(indent);
+                }
+            } else {
+                i += 1;
+            }
+        }
+
+        out.join("\n")
+    }
+}
+
+// Byte offset of the `:` ending a def or class header on this line, if any. `depth`
+// is the bracket depth, carried over from the previous lines of the header.
+fn header_colon(line: &str, depth: &mut i32) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    let mut chars = line.char_indices();
+    while let Some((pos, c)) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => *depth += 1,
+            (None, ')' | ']' | '}') => *depth -= 1,
+            (None, ':') if *depth <= 0 => return Some(pos),
+            _ => {}
+        }
+    }
+    None
+}
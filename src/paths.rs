@@ -0,0 +1,146 @@
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// How document paths are rendered in the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathMode {
+    /// Relative to the repository root, or the current directory outside a repository.
+    Relative,
+    /// Absolute, with `.` and `..` components resolved.
+    Absolute,
+    /// Exactly as the path was passed on the command line (or found by the walk).
+    AsGiven,
+}
+
+impl PathMode {
+    pub fn parse(s: &str) -> Option<PathMode> {
+        match s {
+            "relative" => Some(PathMode::Relative),
+            "absolute" => Some(PathMode::Absolute),
+            "as-given" => Some(PathMode::AsGiven),
+            _ => None,
+        }
+    }
+}
+
+/// Turns filesystem paths into the strings shown in `print_default` / `print_as_xml`.
+#[derive(Clone, Debug)]
+pub struct PathDisplay {
+    mode: PathMode,
+    base: PathBuf,
+    strip_prefix: Option<PathBuf>,
+}
+
+impl PathDisplay {
+    pub fn new(mode: PathMode, strip_prefix: Option<&str>) -> PathDisplay {
+        let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let base = find_repo_root(&cwd).unwrap_or_else(|| cwd.clone());
+        PathDisplay {
+            mode,
+            base,
+            strip_prefix: strip_prefix.map(|p| absolutize(Path::new(p))),
+        }
+    }
+
+    pub fn display(&self, path: &Path) -> String {
+        if let Some(prefix) = &self.strip_prefix {
+            if let Ok(rest) = absolutize(path).strip_prefix(prefix) {
+                return rest.display().to_string();
+            }
+        }
+        match self.mode {
+            PathMode::AsGiven => path.display().to_string(),
+            PathMode::Absolute => absolutize(path).display().to_string(),
+            PathMode::Relative => relative_to(&absolutize(path), &self.base)
+                .display()
+                .to_string(),
+        }
+    }
+}
+
+impl Default for PathDisplay {
+    fn default() -> Self {
+        PathDisplay::new(PathMode::AsGiven, None)
+    }
+}
+
+// Walk up from `start` looking for a directory that contains `.git`.
+fn find_repo_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+// Make `path` absolute and resolve `.` / `..` lexically. We deliberately avoid
+// `canonicalize` so symlinks are not expanded into machine-specific targets.
+pub(crate) fn absolutize(path: &Path) -> PathBuf {
+    let abs = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut out = PathBuf::new();
+    for comp in abs.components() {
+        match comp {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Whether writing to `path` really stays inside `root` once symlinks are followed.
+/// The deepest existing ancestor of each is canonicalized, so a symlink inside
+/// `root` pointing elsewhere is caught. `path` being a symlink itself is refused,
+/// so nothing is ever written through one.
+pub(crate) fn resolves_inside(path: &Path, root: &Path) -> bool {
+    if fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()) {
+        return false;
+    }
+    match (canonicalize_existing(path), canonicalize_existing(root)) {
+        (Some(path), Some(root)) => path.starts_with(root),
+        _ => false,
+    }
+}
+
+// Canonicalize the longest existing prefix of `path` and append the rest. Returns
+// None if some part exists but can't be resolved, such as a dangling symlink.
+fn canonicalize_existing(path: &Path) -> Option<PathBuf> {
+    let path = absolutize(path);
+    let mut base = path.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = fs::canonicalize(base) {
+            return Some(rest.iter().rev().fold(canonical, |p, part| p.join(part)));
+        }
+        if fs::symlink_metadata(base).is_ok() {
+            return None;
+        }
+        rest.push(base.file_name()?);
+        base = base.parent()?;
+    }
+}
+
+// Lexical relative path from `base` to `path`; both must already be absolute.
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let path_comps: Vec<_> = path.components().collect();
+    let base_comps: Vec<_> = base.components().collect();
+    let common = path_comps
+        .iter()
+        .zip(&base_comps)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut out = PathBuf::new();
+    for _ in common..base_comps.len() {
+        out.push("..");
+    }
+    for comp in &path_comps[common..] {
+        out.push(comp);
+    }
+    if out.as_os_str().is_empty() {
+        out.push(".");
+    }
+    out
+}
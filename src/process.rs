@@ -1,7 +1,7 @@
-use std::error::Error;
+use std::fmt;
 use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 
 use walkdir::WalkDir;
 
@@ -10,44 +10,195 @@ use glob::Pattern;
 use colored::Colorize; // If you want colored output for warnings (optional)
                        // cargo add colored = "2" if you choose to use it
 
-// We'll keep track of a global document index for XML output
-// In Python code it was a global; in Rust we can pass &mut i32 or hold in struct.
-struct Context {
+use crate::archive::{archive_kind, for_each_member};
+use crate::cache::{CachedDoc, DiskCache};
+use crate::error::{Error, Result};
+use crate::format::{Document, Formatter, SkipReason};
+use crate::generated::GeneratedFilter;
+use crate::grep::Grep;
+use crate::lang::language_for;
+use crate::meta::MetaFields;
+use crate::notebook::render_notebook;
+use crate::outline::Outliner;
+use crate::paths::{absolutize, PathDisplay};
+use crate::rank::Ranking;
+use crate::redact::{format_counts, Redactor, SecretCounts};
+use crate::slice::{parse_path_arg, resolve, slice_lines, Selector};
+use crate::stats::{estimate_tokens, FileStats, Stats};
+use crate::transform::{compact, strip_comments};
+use crate::watch::ContentCache;
+
+// State shared by everything that emits documents during one run.
+struct Context<'a> {
+    // We'll keep track of a global document index for XML output
     global_index: usize,
+    writer: Box<dyn Write>,
+    formatter: &'a mut dyn Formatter,
+    secrets: SecretCounts,
+    stats: Stats,
+    // Documents held back with `--query` until they can be ranked
+    pending: Vec<Pending>,
+}
+
+struct Pending {
+    display: String,
+    content: String,
+    lang: Option<&'static str>,
+    lines: Option<(usize, usize)>,
+    tokens: usize,
+    meta: Vec<(&'static str, String)>,
+}
+
+impl Context<'_> {
+    fn write(
+        &mut self,
+        display: &str,
+        content: &str,
+        lang: Option<&str>,
+        lines: Option<(usize, usize)>,
+        tokens: usize,
+        meta: &[(&str, String)],
+    ) -> io::Result<()> {
+        let doc = Document {
+            index: self.global_index,
+            path: display,
+            content,
+            lang,
+            lines,
+            meta,
+        };
+        self.formatter.file(&mut self.writer, &doc)?;
+        self.global_index += 1;
+        self.stats
+            .included
+            .push(FileStats::new(display, content, tokens));
+        Ok(())
+    }
+
+    fn skip(&mut self, display: String, reason: SkipReason) -> io::Result<()> {
+        self.formatter
+            .skipped_file(&mut self.writer, &display, &reason)?;
+        self.stats.skipped.push((display, reason));
+        Ok(())
+    }
+}
+
+/// Everything that controls which files are selected and how their paths are shown.
+#[derive(Default)]
+pub struct Options {
+    pub extensions: Vec<String>,
+    /// Extensions to leave out; these win over `extensions`.
+    pub exclude_extensions: Vec<String>,
+    /// Match extensions case-sensitively (`-e md` then won't match `README.MD`).
+    pub case_sensitive_extensions: bool,
+    pub include_hidden: bool,
+    pub ignore_gitignore: bool,
+    pub ignore_patterns: Vec<String>,
+    pub output_file: Option<String>,
+    pub path_display: PathDisplay,
+    /// Skip files larger than this many bytes.
+    pub max_size: Option<u64>,
+    /// Remove comments from source files in languages we recognise.
+    pub strip_comments: bool,
+    /// Trim trailing whitespace and collapse blank-line runs.
+    pub compact: bool,
+    /// Include text outputs (and image placeholders) when rendering `.ipynb` notebooks.
+    pub notebook_outputs: bool,
+    /// Emit only signatures and type definitions for languages with an extractor.
+    pub outliner: Option<Outliner>,
+    /// Replace secrets with `[REDACTED:kind]` before content is emitted.
+    pub redactor: Option<Redactor>,
+    /// Abort instead of redacting when a secret is found.
+    pub fail_on_secrets: bool,
+    /// Warn about missing or unreadable paths and carry on instead of failing.
+    pub keep_going: bool,
+    /// Leave out generated, vendored and lock files found while walking.
+    pub generated: Option<GeneratedFilter>,
+    /// Emit only files matching a `--query`, best match first.
+    pub ranking: Option<Ranking>,
+    /// Keep only files matching `--grep` (or not, with `--grep-invert`).
+    pub grep: Option<Grep>,
+    /// Reuse file contents from an earlier run when the file hasn't changed (`--watch`).
+    pub cache: Option<ContentCache>,
+    /// Keep transformed content and token counts on disk between runs.
+    pub disk_cache: Option<DiskCache>,
+    /// Metadata to attach to each document (`--meta`).
+    pub meta: MetaFields,
+    /// Instruction texts to put before the documents (`--prefix`).
+    pub prefix: Vec<String>,
+    /// Instruction texts to put after the documents (`--suffix`).
+    pub suffix: Vec<String>,
+}
+
+/// One line of a `.gitignore` file, with where it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GitignoreRule {
+    pub pattern: String,
+    /// The `.gitignore` file the rule was read from.
+    pub source: PathBuf,
+    /// 1-based line number in `source`.
+    pub line: usize,
+}
+
+impl fmt::Display for GitignoreRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} ({}:{})",
+            self.pattern,
+            self.source.display(),
+            self.line
+        )
+    }
 }
 
 pub fn process_files(
     paths: &[String],
-    extensions: &[String],
-    include_hidden: bool,
-    ignore_gitignore: bool,
-    ignore_patterns: &[String],
-    output_file: Option<&str>,
-    claude_xml: bool,
-) -> Result<(), Box<dyn Error>> {
-    let mut ctx = Context { global_index: 1 };
+    opts: &Options,
+    formatter: &mut dyn Formatter,
+) -> Result<Stats> {
+    let ignore_gitignore = opts.ignore_gitignore;
+
+    // `path:120-180` and `path#symbol` pick out part of a file
+    let inputs: Vec<(String, Option<Selector>)> = paths.iter().map(|p| parse_path_arg(p)).collect();
+
+    // Check the inputs before any output is written
+    if !opts.keep_going {
+        if let Some((p, _)) = inputs.iter().find(|(p, _)| !Path::new(p).exists()) {
+            return Err(Error::MissingPath(p.to_string()));
+        }
+    }
 
     // Decide where to print (stdout or a file).
-    let mut writer: Box<dyn Write> = if let Some(outfile) = output_file {
+    let writer: Box<dyn Write> = if let Some(outfile) = &opts.output_file {
         Box::new(fs::File::create(outfile)?)
     } else {
         Box::new(io::stdout())
     };
+    let mut ctx = Context {
+        global_index: 1,
+        writer,
+        formatter,
+        secrets: SecretCounts::new(),
+        stats: Stats::default(),
+        pending: Vec::new(),
+    };
 
     // We’ll gather .gitignore rules from each directory as we go, unless ignore_gitignore is true.
-    let mut gitignore_rules: Vec<String> = Vec::new();
+    let mut gitignore_rules: Vec<GitignoreRule> = Vec::new();
 
-    if claude_xml {
-        // We open the top-level <documents> once if cxml is requested and only if we have at least one path
-        if !paths.is_empty() {
-            writeln!(writer, "<documents>")?;
-        }
+    for text in &opts.prefix {
+        ctx.formatter.prefix(&mut ctx.writer, text)?;
     }
+    ctx.formatter.begin(&mut ctx.writer)?;
 
-    for p in paths {
+    for (p, selector) in &inputs {
         let path = Path::new(p);
         if !path.exists() {
-            return Err(format!("Path does not exist: {}", p).into());
+            let msg = format!("Warning: Skipping {:?}: path does not exist", p);
+            eprintln!("{}", msg.red());
+            ctx.skip(p.to_string(), SkipReason::Missing)?;
+            continue;
         }
 
         // If we’re not ignoring .gitignore, read from the directory containing `p`
@@ -57,27 +208,63 @@ pub fn process_files(
             }
         }
 
-        if path.is_file() {
+        if let Some(selector) = selector.as_ref().filter(|_| path.is_file()) {
+            process_slice(&mut ctx, path, selector, opts)?;
+        } else if let Some(kind) = archive_kind(path).filter(|_| path.is_file()) {
+            // Archive members are treated like a directory walk
+            let archive_display = opts.path_display.display(path);
+            for_each_member(path, kind, |name, size, reader| {
+                let member = Path::new(name);
+                let display = format!("{}!/{}", archive_display, name);
+                let reason = in_skipped_dir(member, opts)
+                    .or_else(|| filter_reason(member, opts, &gitignore_rules))
+                    .or_else(|| too_large(size, opts));
+                if let Some(reason) = reason {
+                    return Ok(ctx.skip(display, reason)?);
+                }
+                // The declared size may be a lie, so don't read past the limit either
+                let limit = opts.max_size.map_or(u64::MAX, |max| max.saturating_add(1));
+                let mut bytes = Vec::new();
+                reader.take(limit).read_to_end(&mut bytes)?;
+                if let Some(reason) = too_large(bytes.len() as u64, opts) {
+                    return Ok(ctx.skip(display, reason)?);
+                }
+                match String::from_utf8(bytes) {
+                    Ok(content) => match generated_header(member, &content, opts) {
+                        Some(reason) => Ok(ctx.skip(display, reason)?),
+                        None => Ok(emit_content(
+                            &mut ctx, member, None, &display, content, None, opts,
+                        )?),
+                    },
+                    Err(e) => Ok(skip_unreadable(&mut ctx, &display, SkipReason::Binary, &e)?),
+                }
+            })?;
+        } else if path.is_file() {
             // Single file
-            process_single_file(
-                &mut writer,
-                &mut ctx,
-                path,
-                extensions,
-                include_hidden,
-                ignore_gitignore,
-                &mut gitignore_rules,
-                ignore_patterns,
-                claude_xml,
-            )?;
+            process_single_file(&mut ctx, path, opts, false)?;
         } else if path.is_dir() {
             // Directory recursion
             // We replicate the Python logic with walkdir
-            for entry in WalkDir::new(path) {
+            let mut walker = WalkDir::new(path).into_iter();
+            while let Some(entry) = walker.next() {
                 let entry = match entry {
                     Ok(e) => e,
                     Err(e) => {
-                        eprintln!("Warning: Skipping entry due to error: {}", e);
+                        let display = e
+                            .path()
+                            .map_or_else(|| p.to_string(), |p| opts.path_display.display(p));
+                        // Only the directory that was named on the command line is fatal;
+                        // anything found below it is warned about and skipped
+                        let depth = e.depth();
+                        let err = io::Error::from(e);
+                        if depth == 0 && !opts.keep_going {
+                            return Err(Error::Unreadable {
+                                path: display,
+                                source: err,
+                            });
+                        }
+                        let reason = SkipReason::Unreadable(err.to_string());
+                        skip_unreadable(&mut ctx, &display, reason, &err)?;
                         continue;
                     }
                 };
@@ -86,189 +273,584 @@ pub fn process_files(
 
                 // If it’s a directory, decide whether to skip it
                 if fpath.is_dir() {
-                    // Skip hidden directories if we’re not including hidden
-                    if !include_hidden && is_hidden_dir(fpath) {
+                    // The root was asked for explicitly, so only prune below it
+                    let reason = skipped_dir_reason(fpath, opts).filter(|_| entry.depth() > 0);
+                    if let Some(reason) = reason {
+                        // skip entire directory
+                        walker.skip_current_dir();
+                        let display = opts.path_display.display(fpath);
+                        ctx.skip(format!("{}/", display), reason)?;
                         continue;
                     }
                     // Possibly read .gitignore in subdirectories
-                    if !ignore_gitignore {
+                    if !ignore_gitignore && !skips_gitignore_of(fpath, opts) {
                         read_gitignore(fpath, &mut gitignore_rules);
-                        if should_ignore(fpath, &gitignore_rules) {
-                            // skip entire directory
-                            continue;
-                        }
                     }
                     continue;
                 } else {
                     // It's a file
-                    // Possibly skip if hidden
-                    if !include_hidden && is_hidden_file(fpath) {
-                        continue;
-                    }
-                    // If we’re ignoring .gitignore, skip if it’s in the .gitignore
-                    if !ignore_gitignore && should_ignore(fpath, &gitignore_rules) {
-                        continue;
-                    }
-                    // Skip if matches ignore_patterns
-                    if should_ignore_pattern(fpath, ignore_patterns) {
-                        continue;
-                    }
-                    // Skip if extension doesn’t match
-                    if !extensions.is_empty() && !has_extension(fpath, extensions) {
+                    if let Some(reason) = filter_reason(fpath, opts, &gitignore_rules) {
+                        ctx.skip(opts.path_display.display(fpath), reason)?;
                         continue;
                     }
 
-                    process_single_file(
-                        &mut writer,
-                        &mut ctx,
-                        fpath,
-                        extensions,
-                        include_hidden,
-                        ignore_gitignore,
-                        &mut gitignore_rules,
-                        ignore_patterns,
-                        claude_xml,
-                    )?;
+                    process_single_file(&mut ctx, fpath, opts, true)?;
                 }
             }
         }
     }
 
-    if claude_xml && !paths.is_empty() {
-        writeln!(writer, "</documents>")?;
+    if let Some(ranking) = &opts.ranking {
+        write_ranked(&mut ctx, ranking)?;
     }
 
-    Ok(())
+    ctx.formatter.end(&mut ctx.writer)?;
+    for text in &opts.suffix {
+        ctx.formatter.suffix(&mut ctx.writer, text)?;
+    }
+    ctx.writer.flush()?;
+
+    if !ctx.secrets.is_empty() {
+        eprintln!(
+            "{}",
+            format!("Redacted secrets: {}", format_counts(&ctx.secrets)).yellow()
+        );
+    }
+
+    Ok(ctx.stats)
 }
 
-fn process_single_file(
-    writer: &mut dyn Write,
+// `walked` is false for files named on the command line, which are never treated
+// as generated and which fail the run when they can't be read.
+fn process_single_file(ctx: &mut Context, path: &Path, opts: &Options, walked: bool) -> Result<()> {
+    let display = opts.path_display.display(path);
+
+    let metadata = fs::metadata(path).ok();
+    if let Some(reason) = metadata.as_ref().and_then(|m| too_large(m.len(), opts)) {
+        ctx.skip(display, reason)?;
+        return Ok(());
+    }
+
+    // Attempt to read text
+    let content = match read_text(path, opts) {
+        Ok(c) => c,
+        Err(e) => {
+            let reason = read_error_reason(&e);
+            if !walked && !opts.keep_going && reason != SkipReason::Binary {
+                return Err(Error::Unreadable {
+                    path: display,
+                    source: e,
+                });
+            }
+            return skip_unreadable(ctx, &display, reason, &e);
+        }
+    };
+
+    if let Some(reason) = generated_header(path, &content, opts).filter(|_| walked) {
+        ctx.skip(display, reason)?;
+        return Ok(());
+    }
+
+    emit_content(ctx, path, metadata.as_ref(), &display, content, None, opts)
+}
+
+fn process_slice(
     ctx: &mut Context,
     path: &Path,
-    _extensions: &[String],
-    _include_hidden: bool,
-    _ignore_gitignore: bool,
-    _gitignore_rules: &mut [String],
-    _ignore_patterns: &[String],
-    claude_xml: bool,
-) -> Result<(), Box<dyn Error>> {
-    // Attempt to read text
-    let content = match fs::read_to_string(path) {
+    selector: &Selector,
+    opts: &Options,
+) -> Result<()> {
+    let display = opts.path_display.display(path);
+    let content = match read_text(path, opts) {
         Ok(c) => c,
         Err(e) => {
-            // If it's UnicodeDecodeError in Python, in Rust it might be invalid UTF-8.
-            // We mimic a "Skipping" warning here:
-            eprintln!(
-                "{}",
-                format!("Warning: Skipping file {:?} due to {}", path, e).red()
-            );
-            return Ok(());
+            let reason = read_error_reason(&e);
+            if !opts.keep_going && reason != SkipReason::Binary {
+                return Err(Error::Unreadable {
+                    path: display,
+                    source: e,
+                });
+            }
+            return skip_unreadable(ctx, &display, reason, &e);
         }
     };
+    let (start, end) = resolve(selector, &content, language_for(path))
+        .map_err(|e| Error::Usage(format!("{}: {}", display, e)))?;
+    let content = slice_lines(&content, start, end);
+    let metadata = fs::metadata(path).ok();
+    emit_content(
+        ctx,
+        path,
+        metadata.as_ref(),
+        &display,
+        content,
+        Some((start, end)),
+        opts,
+    )
+}
 
-    if claude_xml {
-        print_as_xml(writer, ctx, path, &content)?;
-    } else {
-        print_default(writer, path, &content)?;
+fn read_text(path: &Path, opts: &Options) -> io::Result<String> {
+    match &opts.cache {
+        Some(cache) => cache.read(path),
+        None => fs::read_to_string(path),
     }
+}
+
+// Write the held back documents best match first, stopping at `--top` and leaving
+// out anything that doesn't fit in `--budget`.
+fn write_ranked(ctx: &mut Context, ranking: &Ranking) -> Result<()> {
+    let pending = std::mem::take(&mut ctx.pending);
+    let docs: Vec<(&str, &str)> = pending
+        .iter()
+        .map(|p| (p.display.as_str(), p.content.as_str()))
+        .collect();
+    let ranked = ranking.rank(&docs);
 
+    let mut matched = vec![false; pending.len()];
+    let mut tokens = 0;
+    for (n, &(i, _)) in ranked.iter().enumerate() {
+        matched[i] = true;
+        let doc = &pending[i];
+        let cost = doc.tokens;
+        let reason = match (ranking.top, ranking.budget) {
+            (Some(top), _) if n >= top => Some(format!("beyond --top {}", top)),
+            (_, Some(budget)) if tokens + cost > budget => {
+                Some(format!("over the --budget of {} tokens", budget))
+            }
+            _ => None,
+        };
+        match reason {
+            Some(reason) => ctx.skip(doc.display.clone(), SkipReason::Cutoff(reason))?,
+            None => {
+                tokens += cost;
+                ctx.write(
+                    &doc.display,
+                    &doc.content,
+                    doc.lang,
+                    doc.lines,
+                    doc.tokens,
+                    &doc.meta,
+                )?;
+            }
+        }
+    }
+
+    for (i, doc) in pending.iter().enumerate() {
+        if !matched[i] {
+            let reason = SkipReason::Cutoff("no match for --query".to_string());
+            ctx.skip(doc.display.clone(), reason)?;
+        }
+    }
     Ok(())
 }
 
-fn print_default(writer: &mut dyn Write, path: &Path, content: &str) -> io::Result<()> {
-    writeln!(writer, "{}", path.display())?;
-    writeln!(writer, "---")?;
-    writeln!(writer, "{}", content)?;
-    writeln!(writer, "---")?;
+// If it's UnicodeDecodeError in Python, in Rust it might be invalid UTF-8.
+fn read_error_reason(e: &io::Error) -> SkipReason {
+    if e.kind() == io::ErrorKind::InvalidData {
+        SkipReason::Binary
+    } else {
+        SkipReason::Unreadable(e.to_string())
+    }
+}
+
+fn skip_unreadable(
+    ctx: &mut Context,
+    display: &str,
+    reason: SkipReason,
+    err: &dyn std::error::Error,
+) -> Result<()> {
+    // We mimic the Python "Skipping" warning here:
+    eprintln!(
+        "{}",
+        format!("Warning: Skipping file {:?} due to {}", display, err).red()
+    );
+    ctx.skip(display.to_string(), reason)?;
     Ok(())
 }
 
-fn print_as_xml(
-    writer: &mut dyn Write,
+// Run the content transforms and hand the document to the formatter. `path` is only
+// used to work out the language, so archive members can pass their inner path (and no
+// `source` metadata).
+fn emit_content(
     ctx: &mut Context,
     path: &Path,
-    content: &str,
-) -> io::Result<()> {
-    writeln!(writer, "<document index=\"{}\">", ctx.global_index)?;
-    writeln!(writer, "<source>{}</source>", path.display())?;
-    writeln!(writer, "<document_content>")?;
-    writeln!(writer, "{}", content)?;
-    writeln!(writer, "</document_content>")?;
-    writeln!(writer, "</document>")?;
-    ctx.global_index += 1;
+    source: Option<&fs::Metadata>,
+    display: &str,
+    content: String,
+    lines: Option<(usize, usize)>,
+    opts: &Options,
+) -> Result<()> {
+    let lang = language_for(path);
+    let mut content = content;
+    if path.extension().is_some_and(|e| e == "ipynb") {
+        match render_notebook(&content, opts.notebook_outputs) {
+            Ok(rendered) => content = rendered,
+            Err(e) => {
+                let msg = format!("Warning: Cannot parse notebook {:?}: {}", path, e);
+                eprintln!("{}", msg.yellow());
+            }
+        }
+    }
+    if let Some(grep) = &opts.grep {
+        if !grep.keeps(&content) {
+            ctx.skip(display.to_string(), SkipReason::Grep)?;
+            return Ok(());
+        }
+        if let Some(excerpt) = grep.excerpt(&content) {
+            content = excerpt;
+        }
+    }
+
+    let CachedDoc {
+        content,
+        tokens,
+        secrets,
+    } = transform(path, source, content, lang, opts);
+    if !secrets.is_empty() {
+        if opts.fail_on_secrets {
+            return Err(Error::SecretsFound {
+                path: display.to_string(),
+                kinds: format_counts(&secrets),
+            });
+        }
+        let msg = format!(
+            "Warning: Redacted {} ({})",
+            display,
+            format_counts(&secrets)
+        );
+        eprintln!("{}", msg.yellow());
+        for (kind, n) in secrets {
+            *ctx.secrets.entry(kind).or_default() += n;
+        }
+    }
+
+    let meta = opts.meta.collect(source.map(|m| (path, m)), &content);
+    if opts.ranking.is_some() {
+        ctx.pending.push(Pending {
+            display: display.to_string(),
+            content,
+            lang,
+            lines,
+            tokens,
+            meta,
+        });
+        return Ok(());
+    }
+    ctx.write(display, &content, lang, lines, tokens, &meta)?;
+
     Ok(())
 }
 
+// Outline, strip comments, compact and redact, and count the tokens of the result.
+// These are the expensive steps, so they go through the on-disk cache when one is
+// enabled and any of them are switched on.
+fn transform(
+    path: &Path,
+    source: Option<&fs::Metadata>,
+    content: String,
+    lang: Option<&str>,
+    opts: &Options,
+) -> CachedDoc {
+    let cached = opts.disk_cache.as_ref().zip(transform_options(opts));
+    let key =
+        cached.map(|(cache, options)| (cache, DiskCache::key(path, source, &content, &options)));
+    if let Some(doc) = key.as_ref().and_then(|(cache, key)| cache.get(key)) {
+        return doc;
+    }
+
+    let mut content = content;
+    if let Some(outline) = opts.outliner.as_ref().and_then(|o| o.outline(&content, lang)) {
+        content = outline;
+    }
+    if opts.strip_comments {
+        content = strip_comments(&content, lang);
+    }
+    if opts.compact {
+        content = compact(&content);
+    }
+    let (content, secrets) = match &opts.redactor {
+        Some(redactor) => redactor.redact(&content),
+        None => (content, SecretCounts::new()),
+    };
+    let doc = CachedDoc {
+        tokens: estimate_tokens(&content),
+        content,
+        secrets,
+    };
+
+    if let Some((cache, key)) = key {
+        // The cache is only an optimisation, so failing to write it isn't an error
+        let _ = cache.put(&key, &doc);
+    }
+    doc
+}
+
+// A description of the transforms that are switched on, or None when there are none
+// and caching isn't worth it.
+fn transform_options(opts: &Options) -> Option<String> {
+    let mut options = Vec::new();
+    if let Some(outliner) = &opts.outliner {
+        options.push(format!("outline={}", outliner.languages().join(",")));
+    }
+    if opts.strip_comments {
+        options.push("strip_comments".to_string());
+    }
+    if opts.compact {
+        options.push("compact".to_string());
+    }
+    if let Some(redactor) = &opts.redactor {
+        options.push(format!("redact:\n{}", redactor.fingerprint()));
+    }
+    (!options.is_empty()).then(|| options.join("\n"))
+}
+
+// Should a directory (and everything under it) be left out? Only generated and
+// vendored directories are; hidden and gitignored ones are still walked, and the
+// files in them judged one by one.
+fn skipped_dir_reason(dir: &Path, opts: &Options) -> Option<SkipReason> {
+    let name = dir.file_name()?.to_string_lossy();
+    let generated = opts.generated.as_ref()?;
+    generated
+        .matching_dir(&name)
+        .map(|d| SkipReason::Generated(format!("{}/", d)))
+}
+
+// The .gitignore of a hidden directory is not read unless hidden files are included.
+fn skips_gitignore_of(dir: &Path, opts: &Options) -> bool {
+    !opts.include_hidden && is_hidden_dir(dir)
+}
+
+// Archive members have no directory entries of their own, so check every parent folder.
+fn in_skipped_dir(member: &Path, opts: &Options) -> Option<SkipReason> {
+    member
+        .ancestors()
+        .skip(1)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .find_map(|dir| skipped_dir_reason(dir, opts))
+}
+
+// Apply the hidden, .gitignore, --ignore and extension filters to a file found by
+// walking a directory or an archive.
+fn filter_reason(
+    path: &Path,
+    opts: &Options,
+    gitignore_rules: &[GitignoreRule],
+) -> Option<SkipReason> {
+    // Possibly skip if hidden
+    if !opts.include_hidden && is_hidden_file(path) {
+        return Some(SkipReason::Hidden);
+    }
+    // Unless we’re ignoring .gitignore, skip if it’s in the .gitignore
+    if !opts.ignore_gitignore {
+        if let Some(rule) = matching_gitignore_rule(path, gitignore_rules) {
+            return Some(SkipReason::Gitignore(rule.clone()));
+        }
+    }
+    // Skip if matches ignore_patterns
+    if let Some(pat) = matching_ignore_pattern(path, &opts.ignore_patterns) {
+        return Some(SkipReason::IgnorePattern(pat.to_string()));
+    }
+    // Skip if extension doesn’t match
+    let case_sensitive = opts.case_sensitive_extensions;
+    if let Some(ext) = matching_extension(path, &opts.exclude_extensions, case_sensitive) {
+        return Some(SkipReason::ExcludedExtension(ext.to_string()));
+    }
+    if !opts.extensions.is_empty()
+        && matching_extension(path, &opts.extensions, case_sensitive).is_none()
+    {
+        return Some(SkipReason::Extension);
+    }
+    let name = path.file_name()?.to_string_lossy();
+    let generated = opts.generated.as_ref()?;
+    generated
+        .matching_file(&name)
+        .map(|f| SkipReason::Generated(f.to_string()))
+}
+
+// Look for a "generated, do not edit" marker at the top of the content.
+fn generated_header(path: &Path, content: &str, opts: &Options) -> Option<SkipReason> {
+    let name = path.file_name()?.to_string_lossy();
+    let generated = opts.generated.as_ref()?;
+    generated
+        .header_marker(&name, content)
+        .map(|m| SkipReason::Generated(format!("{} header", m)))
+}
+
+/// What `process_files` would do with a path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Included,
+    Skipped(SkipReason),
+    /// An ancestor directory is pruned, so the walk never reaches the path.
+    InSkippedDir(PathBuf, SkipReason),
+}
+
+/// Work out why `path` would be included or skipped when walking `root`. The walk is
+/// replayed from `root` down to `path`, picking up `.gitignore` files on the way just
+/// as `process_files` does. A path outside `root` is treated as given explicitly.
+pub fn explain(path: &Path, root: &Path, opts: &Options) -> Result<Verdict> {
+    for p in [path, root] {
+        if !p.exists() {
+            return Err(Error::MissingPath(p.display().to_string()));
+        }
+    }
+
+    let mut gitignore_rules: Vec<GitignoreRule> = Vec::new();
+    if !opts.ignore_gitignore {
+        if let Some(parent) = root.parent() {
+            read_gitignore(parent, &mut gitignore_rules);
+        }
+    }
+
+    let relative = absolutize(path)
+        .strip_prefix(absolutize(root))
+        .map(Path::to_path_buf)
+        .ok()
+        .filter(|rel| !rel.as_os_str().is_empty());
+    let walked = relative.is_some();
+    if let Some(relative) = relative {
+        let mut dir = root.to_path_buf();
+        let mut depth = 0;
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            if dir.is_dir() {
+                if depth > 0 {
+                    if let Some(reason) = skipped_dir_reason(&dir, opts) {
+                        return Ok(Verdict::InSkippedDir(dir, reason));
+                    }
+                }
+                if !opts.ignore_gitignore && !skips_gitignore_of(&dir, opts) {
+                    read_gitignore(&dir, &mut gitignore_rules);
+                }
+            }
+            dir.push(component);
+            depth += 1;
+            if components.peek().is_none() {
+                let reason = if dir.is_dir() {
+                    skipped_dir_reason(&dir, opts)
+                } else {
+                    filter_reason(&dir, opts, &gitignore_rules)
+                };
+                if let Some(reason) = reason {
+                    return Ok(Verdict::Skipped(reason));
+                }
+            }
+        }
+    }
+
+    if path.is_file() {
+        if let Some(reason) = fs::metadata(path)
+            .ok()
+            .and_then(|m| too_large(m.len(), opts))
+        {
+            return Ok(Verdict::Skipped(reason));
+        }
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => return Ok(Verdict::Skipped(read_error_reason(&e))),
+        };
+        if let Some(reason) = generated_header(path, &content, opts).filter(|_| walked) {
+            return Ok(Verdict::Skipped(reason));
+        }
+    }
+    Ok(Verdict::Included)
+}
+
+fn too_large(size: u64, opts: &Options) -> Option<SkipReason> {
+    opts.max_size
+        .filter(|&max| size > max)
+        .map(|_| SkipReason::TooLarge(size))
+}
+
 // Very simplistic .gitignore reading
-fn read_gitignore(dir: &Path, gitignore_rules: &mut Vec<String>) {
+fn read_gitignore(dir: &Path, gitignore_rules: &mut Vec<GitignoreRule>) {
     let mut ignore_file = dir.to_path_buf();
     ignore_file.push(".gitignore");
 
     if ignore_file.is_file() {
         if let Ok(contents) = fs::read_to_string(&ignore_file) {
-            for line in contents.lines() {
+            for (lineno, line) in contents.lines().enumerate() {
                 let line = line.trim();
                 if line.is_empty() || line.starts_with('#') {
                     continue;
                 }
-                gitignore_rules.push(line.to_string());
+                gitignore_rules.push(GitignoreRule {
+                    pattern: line.to_string(),
+                    source: ignore_file.clone(),
+                    line: lineno + 1,
+                });
             }
         }
     }
 }
 
-// Should we ignore based on .gitignore lines
+// Should we ignore based on .gitignore lines? Returns the rule that matched.
 // .gitignore can contain patterns like *.txt or exact filenames. This is simplistic:
-fn should_ignore(path: &Path, gitignore_rules: &[String]) -> bool {
-    let name = match path.file_name() {
-        Some(n) => n.to_string_lossy(),
-        None => return false,
-    };
+fn matching_gitignore_rule<'r>(
+    path: &Path,
+    gitignore_rules: &'r [GitignoreRule],
+) -> Option<&'r GitignoreRule> {
+    let name = path.file_name()?.to_string_lossy();
 
     for rule in gitignore_rules {
+        let pattern = &rule.pattern;
         // If rule ends with "/", it's a directory pattern
-        if rule.ends_with('/') {
-            let dir_rule = &rule[..rule.len() - 1];
+        if let Some(dir_rule) = pattern.strip_suffix('/') {
             if name == dir_rule {
                 // directory match
-                return true;
+                return Some(rule);
             }
         } else {
             // file match
             // We can use glob matching or direct equality
-            if Pattern::new(rule).map_or(false, |pat| pat.matches(&name)) {
-                return true;
+            if Pattern::new(pattern).is_ok_and(|pat| pat.matches(&name)) {
+                return Some(rule);
             }
         }
     }
-    false
+    None
 }
 
 // Check user-specified --ignore patterns
-fn should_ignore_pattern(path: &Path, ignore_patterns: &[String]) -> bool {
-    let name = match path.file_name() {
-        Some(n) => n.to_string_lossy(),
-        None => return false,
-    };
+fn matching_ignore_pattern<'p>(path: &Path, ignore_patterns: &'p [String]) -> Option<&'p str> {
+    let name = path.file_name()?.to_string_lossy();
 
-    for pat in ignore_patterns {
-        if Pattern::new(pat).map_or(false, |p| p.matches(&name)) {
-            return true;
-        }
-    }
-    false
+    ignore_patterns
+        .iter()
+        .find(|pat| Pattern::new(pat).is_ok_and(|p| p.matches(&name)))
+        .map(String::as_str)
 }
 
-fn has_extension(path: &Path, extensions: &[String]) -> bool {
-    if let Some(ext) = path.extension() {
-        let ext_lower = ext.to_string_lossy().to_lowercase();
-        for e in extensions {
-            if e.to_lowercase() == ext_lower {
-                return true;
-            }
-        }
-    }
-    false
+// Extensions are compared against the end of the file name, so multi-part ones
+// like `d.ts` or `tar.gz` work, and a bare name like `Makefile` matches the whole
+// name of an extensionless file. A leading `.` is optional.
+fn matching_extension<'e>(
+    path: &Path,
+    extensions: &'e [String],
+    case_sensitive: bool,
+) -> Option<&'e str> {
+    let name = path.file_name()?.to_string_lossy();
+    let name = if case_sensitive {
+        name.into_owned()
+    } else {
+        name.to_lowercase()
+    };
+
+    extensions
+        .iter()
+        .find(|e| {
+            let ext = e.strip_prefix('.').unwrap_or(e);
+            let ext = if case_sensitive {
+                ext.to_string()
+            } else {
+                ext.to_lowercase()
+            };
+            name == ext
+                || name
+                    .strip_suffix(&ext)
+                    .is_some_and(|stem| stem.len() > 1 && stem.ends_with('.'))
+        })
+        .map(String::as_str)
 }
 
 fn is_hidden_dir(path: &Path) -> bool {
@@ -288,4 +870,3 @@ fn is_hidden_file(path: &Path) -> bool {
         false
     }
 }
-
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+// Standard BM25 parameters.
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+// Path tokens count this many times over content tokens, since a query word in a
+// file name is a strong hint.
+const PATH_WEIGHT: usize = 3;
+
+/// Orders documents by how well they match a free-text query, using BM25 over the
+/// file contents and the tokens of the path. Everything runs locally.
+#[derive(Clone, Debug, Default)]
+pub struct Ranking {
+    pub query: String,
+    /// Keep at most this many documents.
+    pub top: Option<usize>,
+    /// Keep adding documents in rank order while the estimated tokens fit.
+    pub budget: Option<usize>,
+}
+
+impl Ranking {
+    pub fn new(query: &str) -> Ranking {
+        Ranking {
+            query: query.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Indices of the documents that match the query at all, best first. Ties keep
+    /// their original order. `docs` are `(path, content)` pairs.
+    pub fn rank(&self, docs: &[(&str, &str)]) -> Vec<(usize, f64)> {
+        let scores = bm25_scores(&self.query, docs);
+        let mut ranked: Vec<(usize, f64)> = scores
+            .into_iter()
+            .enumerate()
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked
+    }
+}
+
+/// BM25 score of every document for `query`.
+pub fn bm25_scores(query: &str, docs: &[(&str, &str)]) -> Vec<f64> {
+    let mut terms = tokenize(query);
+    terms.sort();
+    terms.dedup();
+
+    let term_freqs: Vec<HashMap<String, usize>> = docs
+        .iter()
+        .map(|(path, content)| {
+            let mut freqs: HashMap<String, usize> = HashMap::new();
+            for token in tokenize(content) {
+                *freqs.entry(token).or_default() += 1;
+            }
+            for token in tokenize(path) {
+                *freqs.entry(token).or_default() += PATH_WEIGHT;
+            }
+            freqs
+        })
+        .collect();
+    let lengths: Vec<f64> = term_freqs
+        .iter()
+        .map(|f| f.values().sum::<usize>() as f64)
+        .collect();
+    let n = docs.len() as f64;
+    let avg_len = lengths.iter().sum::<f64>() / n.max(1.0);
+
+    let idf: Vec<f64> = terms
+        .iter()
+        .map(|t| {
+            let df = term_freqs.iter().filter(|f| f.contains_key(t)).count() as f64;
+            (1.0 + (n - df + 0.5) / (df + 0.5)).ln()
+        })
+        .collect();
+
+    term_freqs
+        .iter()
+        .zip(&lengths)
+        .map(|(freqs, &len)| {
+            terms
+                .iter()
+                .zip(&idf)
+                .map(|(t, idf)| {
+                    let tf = *freqs.get(t).unwrap_or(&0) as f64;
+                    let norm = K1 * (1.0 - B + B * len / avg_len.max(1.0));
+                    idf * tf * (K1 + 1.0) / (tf + norm)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+// Lowercased words, also split at camelCase boundaries, with a trailing plural `s`
+// dropped so "tokens" matches "token".
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let mut start = 0;
+        let chars: Vec<(usize, char)> = word.char_indices().collect();
+        for i in 1..chars.len() {
+            if chars[i].1.is_uppercase() && chars[i - 1].1.is_lowercase() {
+                tokens.push(normalize(&word[start..chars[i].0]));
+                start = chars[i].0;
+            }
+        }
+        if start < word.len() {
+            tokens.push(normalize(&word[start..]));
+        }
+    }
+    tokens
+}
+
+fn normalize(word: &str) -> String {
+    let word = word.to_lowercase();
+    match word.strip_suffix('s') {
+        Some(stem) if stem.len() >= 3 && !stem.ends_with('s') => stem.to_string(),
+        _ => word,
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+/// Number of redactions per secret kind, e.g. `{"aws_access_key": 1}`.
+pub type SecretCounts = BTreeMap<String, usize>;
+
+// A named secret pattern. When the regex has a capture group, only the first group
+// is replaced, so `password = hunter2` keeps its key and becomes
+// `password = [REDACTED:password]`.
+#[derive(Clone, Debug)]
+struct SecretPattern {
+    kind: String,
+    regex: Regex,
+}
+
+lazy_static! {
+    static ref BUILTIN_PATTERNS: Vec<SecretPattern> = [
+        (
+            "private_key",
+            r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+        ),
+        ("aws_access_key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+        (
+            "aws_secret_key",
+            r#"(?i)aws_secret_access_key\s*[:=]\s*["']?([A-Za-z0-9/+=]{40})"#,
+        ),
+        ("github_token", r"\bgh[pousr]_[A-Za-z0-9]{36,}\b"),
+        (
+            "jwt",
+            r"\beyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}",
+        ),
+        (
+            "password",
+            r#"(?i)\b(?:password|passwd|pwd|secret|api_?key|access_?token|auth_?token)\s*[:=]\s*["']?([^\s"',;]{4,})"#,
+        ),
+    ]
+    .iter()
+    .map(|(kind, re)| SecretPattern {
+        kind: kind.to_string(),
+        regex: Regex::new(re).unwrap(),
+    })
+    .collect();
+
+    // Candidate tokens for the high-entropy check: long runs of base64-ish characters.
+    static ref ENTROPY_CANDIDATE: Regex = Regex::new(r"[A-Za-z0-9+/=_-]{32,}").unwrap();
+}
+
+// Shannon entropy (bits per character) above which a candidate token is treated
+// as a secret. Random base64 scores around 5; identifiers and hex digests stay below 4.2.
+const ENTROPY_THRESHOLD: f64 = 4.3;
+
+/// Replaces secrets in file content with `[REDACTED:kind]` markers.
+#[derive(Clone, Debug)]
+pub struct Redactor {
+    patterns: Vec<SecretPattern>,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Redactor {
+            patterns: BUILTIN_PATTERNS.clone(),
+        }
+    }
+}
+
+impl Redactor {
+    /// Add user-defined patterns from a config file with one `kind = regex` per line.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn add_config(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read redact config {}: {}", path.display(), e))?;
+        for (lineno, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((kind, re)) = line.split_once('=') else {
+                return Err(format!(
+                    "{}:{}: expected `kind = regex`",
+                    path.display(),
+                    lineno + 1
+                )
+                .into());
+            };
+            let regex = Regex::new(re.trim())
+                .map_err(|e| format!("{}:{}: {}", path.display(), lineno + 1, e))?;
+            self.patterns.push(SecretPattern {
+                kind: kind.trim().to_string(),
+                regex,
+            });
+        }
+        Ok(())
+    }
+
+    /// The patterns in use as `kind=regex` lines, for telling redactor setups apart.
+    pub fn fingerprint(&self) -> String {
+        self.patterns
+            .iter()
+            .map(|p| format!("{}={}\n", p.kind, p.regex.as_str()))
+            .collect()
+    }
+
+    /// Return the redacted content and how many secrets of each kind were replaced.
+    pub fn redact(&self, content: &str) -> (String, SecretCounts) {
+        let mut counts = SecretCounts::new();
+        let mut text = content.to_string();
+
+        for pat in &self.patterns {
+            let mut n = 0;
+            let replaced = pat.regex.replace_all(&text, |caps: &Captures| {
+                n += 1;
+                let marker = format!("[REDACTED:{}]", pat.kind);
+                match caps.get(1) {
+                    Some(secret) => {
+                        let whole = caps.get(0).unwrap();
+                        let head = &whole.as_str()[..secret.start() - whole.start()];
+                        let tail = &whole.as_str()[secret.end() - whole.start()..];
+                        format!("{}{}{}", head, marker, tail)
+                    }
+                    None => marker,
+                }
+            });
+            if n > 0 {
+                text = replaced.into_owned();
+                *counts.entry(pat.kind.clone()).or_default() += n;
+            }
+        }
+
+        let mut n = 0;
+        let replaced = ENTROPY_CANDIDATE.replace_all(&text, |caps: &Captures| {
+            let token = &caps[0];
+            if looks_random(token) {
+                n += 1;
+                "[REDACTED:high_entropy]".to_string()
+            } else {
+                token.to_string()
+            }
+        });
+        if n > 0 {
+            text = replaced.into_owned();
+            *counts.entry("high_entropy".to_string()).or_default() += n;
+        }
+
+        (text, counts)
+    }
+}
+
+fn looks_random(token: &str) -> bool {
+    let has_digit = token.bytes().any(|b| b.is_ascii_digit());
+    let has_alpha = token.bytes().any(|b| b.is_ascii_alphabetic());
+    has_digit && has_alpha && shannon_entropy(token) > ENTROPY_THRESHOLD
+}
+
+fn shannon_entropy(s: &str) -> f64 {
+    let mut freq = [0usize; 256];
+    for b in s.bytes() {
+        freq[b as usize] += 1;
+    }
+    let len = s.len() as f64;
+    freq.iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Render counts as `kind=n, kind=n` for warnings.
+pub fn format_counts(counts: &SecretCounts) -> String {
+    counts
+        .iter()
+        .map(|(kind, n)| format!("{}={}", kind, n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
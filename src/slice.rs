@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::outline::skip_item;
+
+/// Part of a file asked for on the command line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Selector {
+    /// `path:120-180`, or `path:120` for a single line (1-based, inclusive).
+    Lines(usize, usize),
+    /// `path#name`: a Rust function, struct, enum, trait, impl, ...
+    Symbol(String),
+}
+
+lazy_static! {
+    static ref LINE_RANGE: Regex = Regex::new(r"^(\d+)(?:-(\d+))?$").unwrap();
+    static ref RUST_ITEM: Regex = Regex::new(
+        r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:(?:async|const|unsafe|default|extern\s+\S+)\s+)*(?:fn|struct|enum|trait|union|type|mod|const|static|macro_rules!)\s+(\w+)"
+    )
+    .unwrap();
+    static ref RUST_IMPL: Regex = Regex::new(
+        r"^\s*(?:unsafe\s+)?impl\b(?:<[^>]*>)?\s+(?:[\w:<>, ]+\s+for\s+)?(?:\w+::)*(\w+)"
+    )
+    .unwrap();
+}
+
+/// Split `path:120-180` or `path#symbol` into the path and the selector. An argument
+/// naming an existing file is never split, so odd file names still work.
+pub fn parse_path_arg(arg: &str) -> (String, Option<Selector>) {
+    if Path::new(arg).exists() {
+        return (arg.to_string(), None);
+    }
+    if let Some((path, symbol)) = arg.rsplit_once('#') {
+        if !symbol.is_empty() && Path::new(path).is_file() {
+            return (path.to_string(), Some(Selector::Symbol(symbol.to_string())));
+        }
+    }
+    if let Some((path, range)) = arg.rsplit_once(':') {
+        if let Some(caps) = LINE_RANGE.captures(range) {
+            let start: usize = caps[1].parse().unwrap_or(0);
+            let end = caps
+                .get(2)
+                .map_or(start, |m| m.as_str().parse().unwrap_or(0));
+            if start > 0 && end >= start {
+                return (path.to_string(), Some(Selector::Lines(start, end)));
+            }
+        }
+    }
+    (arg.to_string(), None)
+}
+
+/// The 1-based, inclusive line range `selector` picks out of `content`. Ranges past
+/// the end of the file are clamped.
+pub fn resolve(
+    selector: &Selector,
+    content: &str,
+    lang: Option<&str>,
+) -> Result<(usize, usize), String> {
+    let line_count = content.lines().count();
+    match selector {
+        Selector::Lines(start, end) => {
+            if *start > line_count {
+                return Err(format!(
+                    "Line {} is past the end of the file ({} lines)",
+                    start, line_count
+                ));
+            }
+            Ok((*start, (*end).min(line_count)))
+        }
+        Selector::Symbol(name) => {
+            if lang != Some("rust") {
+                return Err(format!(
+                    "Can't look up symbol {:?}: only Rust files are supported",
+                    name
+                ));
+            }
+            rust_symbol(content, name).ok_or_else(|| format!("Symbol {:?} not found", name))
+        }
+    }
+}
+
+/// The lines `start..=end` of `content`.
+pub fn slice_lines(content: &str, start: usize, end: usize) -> String {
+    content
+        .lines()
+        .skip(start - 1)
+        .take(end + 1 - start)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Find the first item named `name`, including the doc comments and attributes above
+// it, and return its line range.
+fn rust_symbol(content: &str, name: &str) -> Option<(usize, usize)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.iter().position(|line| {
+        [&*RUST_ITEM, &*RUST_IMPL]
+            .iter()
+            .any(|re| re.captures(line).is_some_and(|c| &c[1] == name))
+    })?;
+    let end = skip_item(&lines, start, &mut 0);
+
+    let mut first = start;
+    while first > 0 {
+        let above = lines[first - 1].trim_start();
+        if above.starts_with("///") || above.starts_with("#[") {
+            first -= 1;
+        } else {
+            break;
+        }
+    }
+    Some((first + 1, end))
+}
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use crate::format::SkipReason;
+
+/// Rough token estimate: about four characters per token for English text and code.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Size of one document as emitted (after any transforms).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileStats {
+    pub path: String,
+    pub bytes: usize,
+    pub lines: usize,
+    pub tokens: usize,
+}
+
+impl FileStats {
+    /// `tokens` is passed in because it may come from the cache rather than
+    /// `estimate_tokens`.
+    pub fn new(path: &str, content: &str, tokens: usize) -> FileStats {
+        FileStats {
+            path: path.to_string(),
+            bytes: content.len(),
+            lines: content.lines().count(),
+            tokens,
+        }
+    }
+}
+
+/// What a run included and skipped, returned by `process_files`.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub included: Vec<FileStats>,
+    pub skipped: Vec<(String, SkipReason)>,
+}
+
+impl Stats {
+    pub fn total_bytes(&self) -> usize {
+        self.included.iter().map(|f| f.bytes).sum()
+    }
+
+    pub fn total_lines(&self) -> usize {
+        self.included.iter().map(|f| f.lines).sum()
+    }
+
+    pub fn total_tokens(&self) -> usize {
+        self.included.iter().map(|f| f.tokens).sum()
+    }
+
+    /// Paths that were missing or could not be read (with `--keep-going`).
+    pub fn failed(&self) -> usize {
+        self.skipped
+            .iter()
+            .filter(|(_, r)| matches!(r, SkipReason::Missing | SkipReason::Unreadable(_)))
+            .count()
+    }
+
+    /// The `n` largest included files by bytes, largest first.
+    pub fn largest(&self, n: usize) -> Vec<&FileStats> {
+        let mut files: Vec<&FileStats> = self.included.iter().collect();
+        files.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+        files.truncate(n);
+        files
+    }
+
+    /// Human readable report for stderr.
+    pub fn report(&self, top: usize) -> String {
+        let mut out = format!(
+            "Included {} files: {} bytes, {} lines, ~{} tokens\n",
+            self.included.len(),
+            self.total_bytes(),
+            self.total_lines(),
+            self.total_tokens()
+        );
+
+        if !self.skipped.is_empty() {
+            let mut by_kind: BTreeMap<&str, usize> = BTreeMap::new();
+            for (_, reason) in &self.skipped {
+                *by_kind.entry(reason.kind()).or_default() += 1;
+            }
+            let summary: Vec<String> = by_kind
+                .iter()
+                .map(|(kind, n)| format!("{} {}", n, kind))
+                .collect();
+            out.push_str(&format!(
+                "Skipped {} ({}):\n",
+                self.skipped.len(),
+                summary.join(", ")
+            ));
+            for (path, reason) in &self.skipped {
+                out.push_str(&format!("  {} ({})\n", path, reason));
+            }
+        }
+
+        let largest = self.largest(top);
+        if !largest.is_empty() {
+            out.push_str(&format!("Largest {} files:\n", largest.len()));
+            for f in largest {
+                out.push_str(&format!(
+                    "  {:>10} bytes  ~{:>8} tokens  {}\n",
+                    f.bytes, f.tokens, f.path
+                ));
+            }
+        }
+        out
+    }
+
+    /// The same report as JSON.
+    pub fn to_json(&self, top: usize) -> Value {
+        let file = |f: &FileStats| json!({"path": f.path, "bytes": f.bytes, "lines": f.lines, "tokens": f.tokens});
+        json!({
+            "included": {
+                "files": self.included.len(),
+                "bytes": self.total_bytes(),
+                "lines": self.total_lines(),
+                "tokens": self.total_tokens(),
+            },
+            "files": self.included.iter().map(file).collect::<Vec<_>>(),
+            "skipped": self.skipped.iter().map(|(path, reason)| json!({
+                "path": path,
+                "reason": reason.kind(),
+                "detail": reason.to_string(),
+            })).collect::<Vec<_>>(),
+            "largest": self.largest(top).into_iter().map(file).collect::<Vec<_>>(),
+        })
+    }
+}
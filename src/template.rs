@@ -0,0 +1,159 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// A user supplied output template.
+//
+// Templates are plain text with `{{variable}}` placeholders. Three optional sections
+// control where text goes:
+//
+//     {{#header}} ... {{/header}}   printed once before the first file
+//     {{#file}} ... {{/file}}       printed for every file
+//     {{#footer}} ... {{/footer}}   printed once after the last file
+//
+// Without a `{{#file}}` section, whatever is left outside header/footer is the file
+// section. File variables are `path`, `content`, `index`, `lang`, `lines` and
+// `range` (e.g. `120-180` for a `path:120-180` slice, empty otherwise); the footer
+// can use `count`.
+#[derive(Clone, Debug)]
+pub struct Template {
+    header: Vec<Piece>,
+    file: Vec<Piece>,
+    footer: Vec<Piece>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Piece {
+    Text(String),
+    Var(String),
+}
+
+const FILE_VARS: &[&str] = &["path", "content", "index", "lang", "lines", "range"];
+const FOOTER_VARS: &[&str] = &["count"];
+
+/// Values available to the file section of a template.
+pub struct FileVars<'a> {
+    pub path: &'a str,
+    pub content: &'a str,
+    pub index: usize,
+    pub lang: &'a str,
+    pub range: String,
+}
+
+impl Template {
+    pub fn from_file(path: &Path) -> Result<Template, Box<dyn Error>> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read template {}: {}", path.display(), e))?;
+        Template::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> Result<Template, Box<dyn Error>> {
+        let (header, rest) = take_section(source, "header")?;
+        let (footer, rest) = take_section(&rest, "footer")?;
+        let (file, rest) = take_section(&rest, "file")?;
+        let file = match file {
+            Some(f) => {
+                if !rest.trim().is_empty() {
+                    return Err("Template has text outside of its {{#...}} sections".into());
+                }
+                f
+            }
+            None => rest,
+        };
+
+        Ok(Template {
+            header: parse_pieces(&header.unwrap_or_default(), &[])?,
+            file: parse_pieces(&file, FILE_VARS)?,
+            footer: parse_pieces(&footer.unwrap_or_default(), FOOTER_VARS)?,
+        })
+    }
+
+    pub fn render_header(&self) -> String {
+        render(&self.header, |_| String::new())
+    }
+
+    pub fn render_file(&self, vars: &FileVars) -> String {
+        render(&self.file, |name| match name {
+            "path" => vars.path.to_string(),
+            "content" => vars.content.to_string(),
+            "index" => vars.index.to_string(),
+            "lang" => vars.lang.to_string(),
+            "lines" => vars.content.lines().count().to_string(),
+            "range" => vars.range.clone(),
+            _ => String::new(),
+        })
+    }
+
+    pub fn render_footer(&self, count: usize) -> String {
+        render(&self.footer, |name| match name {
+            "count" => count.to_string(),
+            _ => String::new(),
+        })
+    }
+}
+
+// Cut `{{#name}}...{{/name}}` out of `source`, returning the section body and the
+// remaining text. A newline directly after either tag is dropped so that tags can
+// sit on their own lines.
+fn take_section(source: &str, name: &str) -> Result<(Option<String>, String), Box<dyn Error>> {
+    let open = format!("{{{{#{}}}}}", name);
+    let close = format!("{{{{/{}}}}}", name);
+
+    let Some(start) = source.find(&open) else {
+        return Ok((None, source.to_string()));
+    };
+    let body_start = skip_newline(source, start + open.len());
+    let Some(end) = source[body_start..].find(&close).map(|i| body_start + i) else {
+        return Err(format!("Template section {} is not closed with {}", open, close).into());
+    };
+    let after = skip_newline(source, end + close.len());
+
+    let body = source[body_start..end].to_string();
+    let rest = format!("{}{}", &source[..start], &source[after..]);
+    Ok((Some(body), rest))
+}
+
+fn skip_newline(s: &str, pos: usize) -> usize {
+    if s[pos..].starts_with("\r\n") {
+        pos + 2
+    } else if s[pos..].starts_with('\n') {
+        pos + 1
+    } else {
+        pos
+    }
+}
+
+fn parse_pieces(source: &str, allowed: &[&str]) -> Result<Vec<Piece>, Box<dyn Error>> {
+    let mut pieces = Vec::new();
+    let mut rest = source;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            pieces.push(Piece::Text(rest[..start].to_string()));
+        }
+        let Some(len) = rest[start + 2..].find("}}") else {
+            return Err("Unclosed {{ in template".into());
+        };
+        let name = rest[start + 2..start + 2 + len].trim();
+        if !allowed.contains(&name) {
+            return Err(format!("Unknown template variable {{{{{}}}}}", name).into());
+        }
+        pieces.push(Piece::Var(name.to_string()));
+        rest = &rest[start + 2 + len + 2..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest.to_string()));
+    }
+    Ok(pieces)
+}
+
+fn render(pieces: &[Piece], lookup: impl Fn(&str) -> String) -> String {
+    let mut out = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(t) => out.push_str(t),
+            Piece::Var(v) => out.push_str(&lookup(v)),
+        }
+    }
+    out
+}
@@ -0,0 +1,436 @@
+// Token-saving content transforms: comment stripping and whitespace compaction.
+
+// How a language spells its comments and string literals. We only need enough of a
+// lexer to tell comments apart from strings that happen to contain comment markers.
+#[derive(Clone, Copy)]
+struct Syntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+    nested_blocks: bool,
+    // (delimiter, backslash escapes, may span lines)
+    quotes: &'static [(char, bool, bool)],
+    // `#` only starts a comment at line start or after whitespace (shell, YAML).
+    hash_needs_space: bool,
+    // Rust: 'c' char literals vs 'a lifetimes, and r#"raw"# strings.
+    rust: bool,
+    // Python and TOML: """ and ''' strings.
+    triple_quotes: bool,
+    // JavaScript: /regex/ literals.
+    js_regex: bool,
+}
+
+const C_QUOTES: &[(char, bool, bool)] = &[('"', true, false), ('\'', true, false)];
+
+const RUST: Syntax = Syntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+    nested_blocks: true,
+    quotes: &[('"', true, true)],
+    hash_needs_space: false,
+    rust: true,
+    triple_quotes: false,
+    js_regex: false,
+};
+
+const C_LIKE: Syntax = Syntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+    nested_blocks: false,
+    quotes: C_QUOTES,
+    hash_needs_space: false,
+    rust: false,
+    triple_quotes: false,
+    js_regex: false,
+};
+
+const JS: Syntax = Syntax {
+    quotes: &[('"', true, false), ('\'', true, false), ('`', true, true)],
+    js_regex: true,
+    ..C_LIKE
+};
+
+const GO: Syntax = Syntax {
+    quotes: &[('"', true, false), ('\'', true, false), ('`', false, true)],
+    ..C_LIKE
+};
+
+const PYTHON: Syntax = Syntax {
+    line: &["#"],
+    block: None,
+    nested_blocks: false,
+    quotes: C_QUOTES,
+    hash_needs_space: false,
+    rust: false,
+    triple_quotes: true,
+    js_regex: false,
+};
+
+const SHELL: Syntax = Syntax {
+    quotes: &[('"', true, true), ('\'', false, true)],
+    hash_needs_space: true,
+    triple_quotes: false,
+    ..PYTHON
+};
+
+const TOML: Syntax = Syntax {
+    quotes: &[('"', true, false), ('\'', false, false)],
+    ..PYTHON
+};
+
+const YAML: Syntax = Syntax {
+    quotes: &[('"', true, false), ('\'', false, false)],
+    hash_needs_space: true,
+    triple_quotes: false,
+    ..PYTHON
+};
+
+// CSS only has block comments; `//` is just text, as in `url(http://...)`.
+const CSS: Syntax = Syntax {
+    line: &[],
+    ..C_LIKE
+};
+
+const SQL: Syntax = Syntax {
+    line: &["--"],
+    quotes: &[('\'', false, true), ('"', false, false)],
+    ..C_LIKE
+};
+
+fn syntax_for(lang: &str) -> Option<Syntax> {
+    let syntax = match lang {
+        "rust" => RUST,
+        "c" | "cpp" | "java" | "kotlin" | "swift" | "csharp" | "scss" => C_LIKE,
+        "css" => CSS,
+        "javascript" | "typescript" => JS,
+        "go" => GO,
+        "python" => PYTHON,
+        "bash" | "ruby" | "makefile" | "dockerfile" | "cmake" | "nix" => SHELL,
+        "toml" => TOML,
+        "yaml" => YAML,
+        "sql" => SQL,
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Remove line and block comments from `content` written in `lang` (as returned by
+/// `language_for`). Lines that only held a comment are dropped entirely. Content in
+/// languages we don't know is returned unchanged.
+pub fn strip_comments(content: &str, lang: Option<&str>) -> String {
+    match lang.and_then(syntax_for) {
+        Some(syntax) => Stripper::new(content, syntax).run(),
+        None => content.to_string(),
+    }
+}
+
+/// Trim trailing whitespace and collapse runs of blank lines into one.
+pub fn compact(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut blank_run = false;
+    for line in content.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            if !blank_run && !out.is_empty() {
+                out.push('\n');
+            }
+            blank_run = true;
+            continue;
+        }
+        blank_run = false;
+        out.push_str(line);
+        out.push('\n');
+    }
+    // Drop the trailing newline (and any blank line before it) to match
+    // how `print_default` adds its own.
+    while out.ends_with('\n') {
+        out.pop();
+    }
+    out
+}
+
+struct Stripper {
+    chars: Vec<char>,
+    syntax: Syntax,
+    pos: usize,
+    // Finished lines, each flagged with whether a comment was removed from it.
+    lines: Vec<(String, bool)>,
+    cur: String,
+    touched: bool,
+}
+
+impl Stripper {
+    fn new(content: &str, syntax: Syntax) -> Stripper {
+        Stripper {
+            chars: content.chars().collect(),
+            syntax,
+            pos: 0,
+            lines: Vec::new(),
+            cur: String::new(),
+            touched: false,
+        }
+    }
+
+    fn run(mut self) -> String {
+        while self.pos < self.chars.len() {
+            let c = self.chars[self.pos];
+            if c == '\n' {
+                self.newline();
+                self.pos += 1;
+            } else if self.at_block_comment() {
+                self.skip_block_comment();
+            } else if self.at_line_comment() {
+                self.touched = true;
+                while self.pos < self.chars.len() && self.chars[self.pos] != '\n' {
+                    self.pos += 1;
+                }
+            } else if self.syntax.rust && self.at_raw_string() {
+                self.copy_raw_string();
+            } else if self.syntax.rust && c == '\'' {
+                self.copy_char_or_lifetime();
+            } else if self.syntax.triple_quotes && self.at_triple_quote() {
+                self.copy_triple_quoted();
+            } else if let Some(&(delim, escapes, multiline)) =
+                self.syntax.quotes.iter().find(|q| q.0 == c)
+            {
+                self.copy_quoted(delim, escapes, multiline);
+            } else if self.syntax.js_regex && c == '/' && self.regex_allowed() {
+                self.copy_regex();
+            } else {
+                self.cur.push(c);
+                self.pos += 1;
+            }
+        }
+        if !self.cur.is_empty() || self.touched {
+            self.newline();
+        }
+
+        let ends_with_newline = self.chars.last() == Some(&'\n');
+        let kept: Vec<String> = self
+            .lines
+            .into_iter()
+            .filter_map(|(line, touched)| {
+                if !touched {
+                    Some(line)
+                } else if line.trim().is_empty() {
+                    None
+                } else {
+                    Some(line.trim_end().to_string())
+                }
+            })
+            .collect();
+        let mut out = kept.join("\n");
+        if ends_with_newline && !out.is_empty() {
+            out.push('\n');
+        }
+        out
+    }
+
+    fn newline(&mut self) {
+        let line = std::mem::take(&mut self.cur);
+        self.lines.push((line, self.touched));
+        self.touched = false;
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn prev(&self) -> Option<char> {
+        self.pos.checked_sub(1).map(|i| self.chars[i])
+    }
+
+    fn at_block_comment(&self) -> bool {
+        self.syntax
+            .block
+            .is_some_and(|(open, _)| self.starts_with(open))
+    }
+
+    fn skip_block_comment(&mut self) {
+        let (open, close) = self.syntax.block.unwrap();
+        self.touched = true;
+        self.pos += open.chars().count();
+        let mut depth = 1;
+        while self.pos < self.chars.len() && depth > 0 {
+            if self.syntax.nested_blocks && self.starts_with(open) {
+                depth += 1;
+                self.pos += open.chars().count();
+            } else if self.starts_with(close) {
+                depth -= 1;
+                self.pos += close.chars().count();
+            } else {
+                if self.chars[self.pos] == '\n' {
+                    self.newline();
+                    self.touched = true;
+                }
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn at_line_comment(&self) -> bool {
+        self.syntax.line.iter().any(|marker| {
+            if !self.starts_with(marker) {
+                return false;
+            }
+            if *marker != "#" {
+                return true;
+            }
+            // Keep shebang lines
+            if self.pos == 0 && self.starts_with("#!") {
+                return false;
+            }
+            !self.syntax.hash_needs_space
+                || self
+                    .prev()
+                    .is_none_or(|p| p.is_whitespace() || ";|&(".contains(p))
+        })
+    }
+
+    fn copy(&mut self, n: usize) {
+        for _ in 0..n {
+            if let Some(&c) = self.chars.get(self.pos) {
+                if c == '\n' {
+                    self.newline();
+                } else {
+                    self.cur.push(c);
+                }
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn copy_quoted(&mut self, delim: char, escapes: bool, multiline: bool) {
+        self.copy(1);
+        while let Some(&c) = self.chars.get(self.pos) {
+            if c == '\n' && !multiline {
+                return;
+            }
+            if escapes && c == '\\' {
+                self.copy(2);
+                continue;
+            }
+            self.copy(1);
+            if c == delim {
+                return;
+            }
+        }
+    }
+
+    fn at_triple_quote(&self) -> bool {
+        self.starts_with("\"\"\"") || self.starts_with("'''")
+    }
+
+    fn copy_triple_quoted(&mut self) {
+        let delim: String = self.chars[self.pos..self.pos + 3].iter().collect();
+        self.copy(3);
+        while self.pos < self.chars.len() {
+            if self.chars[self.pos] == '\\' {
+                self.copy(2);
+            } else if self.starts_with(&delim) {
+                self.copy(3);
+                return;
+            } else {
+                self.copy(1);
+            }
+        }
+    }
+
+    // r"..", r#".."#, br#".."#
+    fn at_raw_string(&self) -> bool {
+        let mut i = self.pos;
+        if self.chars[i] == 'b' {
+            i += 1;
+        }
+        if self.chars.get(i) != Some(&'r') {
+            return false;
+        }
+        if self
+            .prev()
+            .is_some_and(|p| p.is_alphanumeric() || p == '_')
+        {
+            return false;
+        }
+        i += 1;
+        while self.chars.get(i) == Some(&'#') {
+            i += 1;
+        }
+        self.chars.get(i) == Some(&'"')
+    }
+
+    fn copy_raw_string(&mut self) {
+        while self.chars[self.pos] != '#' && self.chars[self.pos] != '"' {
+            self.copy(1);
+        }
+        let mut hashes = 0;
+        while self.chars[self.pos] == '#' {
+            hashes += 1;
+            self.copy(1);
+        }
+        self.copy(1);
+        let close: String = std::iter::once('"')
+            .chain(std::iter::repeat_n('#', hashes))
+            .collect();
+        while self.pos < self.chars.len() {
+            if self.starts_with(&close) {
+                self.copy(close.len());
+                return;
+            }
+            self.copy(1);
+        }
+    }
+
+    // 'x' and '\n' are char literals; 'a on its own is a lifetime or label.
+    fn copy_char_or_lifetime(&mut self) {
+        if self.chars.get(self.pos + 1) == Some(&'\\') {
+            self.copy(3);
+            while self.pos < self.chars.len() && self.chars[self.pos] != '\'' {
+                if self.chars[self.pos] == '\n' {
+                    return;
+                }
+                self.copy(1);
+            }
+            self.copy(1);
+        } else if self.chars.get(self.pos + 2) == Some(&'\'') {
+            self.copy(3);
+        } else {
+            self.copy(1);
+        }
+    }
+
+    // A `/` starts a regex literal when it can't be a division, i.e. after an operator
+    // or opening bracket rather than after a value.
+    fn regex_allowed(&self) -> bool {
+        let before = self.chars[..self.pos]
+            .iter()
+            .rev()
+            .find(|c| **c != ' ' && **c != '\t');
+        match before {
+            None | Some('\n') => true,
+            Some(c) => "(,=:[!&|?{};+-*%<>~^".contains(*c),
+        }
+    }
+
+    fn copy_regex(&mut self) {
+        self.copy(1);
+        let mut in_class = false;
+        while let Some(&c) = self.chars.get(self.pos) {
+            match c {
+                '\n' => return,
+                '\\' => {
+                    self.copy(2);
+                    continue;
+                }
+                '[' => in_class = true,
+                ']' => in_class = false,
+                '/' if !in_class => {
+                    self.copy(1);
+                    return;
+                }
+                _ => {}
+            }
+            self.copy(1);
+        }
+    }
+}
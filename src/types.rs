@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+// Built-in presets, written like ripgrep's type definitions: `*.ext` globs and
+// well-known file names.
+const BUILTIN_TYPES: &[(&str, &[&str])] = &[
+    ("c", &["*.c", "*.h"]),
+    (
+        "config",
+        &[
+            "*.toml",
+            "*.yaml",
+            "*.yml",
+            "*.json",
+            "*.ini",
+            "*.cfg",
+            "*.conf",
+            "*.env.example",
+        ],
+    ),
+    (
+        "cpp",
+        &["*.cpp", "*.cc", "*.cxx", "*.hpp", "*.hh", "*.hxx", "*.h"],
+    ),
+    (
+        "docker",
+        &[
+            "Dockerfile",
+            "*.dockerfile",
+            "docker-compose.yml",
+            "docker-compose.yaml",
+        ],
+    ),
+    (
+        "docs",
+        &[
+            "*.md",
+            "*.markdown",
+            "*.rst",
+            "*.txt",
+            "*.adoc",
+            "README",
+            "LICENSE",
+            "CHANGELOG",
+        ],
+    ),
+    ("go", &["*.go", "go.mod", "go.sum"]),
+    ("java", &["*.java", "pom.xml", "*.gradle"]),
+    ("js", &["*.js", "*.mjs", "*.cjs", "*.jsx"]),
+    ("make", &["Makefile", "makefile", "GNUmakefile", "*.mk"]),
+    (
+        "python",
+        &[
+            "*.py",
+            "*.pyi",
+            "pyproject.toml",
+            "requirements.txt",
+            "setup.cfg",
+        ],
+    ),
+    ("ruby", &["*.rb", "Gemfile", "Rakefile", "*.gemspec"]),
+    ("rust", &["*.rs", "Cargo.toml"]),
+    ("shell", &["*.sh", "*.bash", "*.zsh", "*.fish"]),
+    ("sql", &["*.sql"]),
+    ("ts", &["*.ts", "*.tsx", "*.mts", "*.cts"]),
+    (
+        "web",
+        &[
+            "*.html", "*.htm", "*.css", "*.scss", "*.sass", "*.less", "*.js", "*.jsx", "*.ts",
+            "*.tsx", "*.vue", "*.svelte",
+        ],
+    ),
+];
+
+/// Named sets of extensions and file names for `--type`.
+#[derive(Clone, Debug)]
+pub struct FileTypes {
+    types: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for FileTypes {
+    fn default() -> Self {
+        let types = BUILTIN_TYPES
+            .iter()
+            .map(|(name, globs)| {
+                (
+                    name.to_string(),
+                    globs.iter().map(|g| g.to_string()).collect(),
+                )
+            })
+            .collect();
+        FileTypes { types }
+    }
+}
+
+impl FileTypes {
+    /// Add globs to a type from a `name:glob[,glob...]` spec, creating the type if
+    /// needed. Only `*.ext` globs and plain file names are supported.
+    pub fn add(&mut self, spec: &str) -> Result<(), String> {
+        let Some((name, globs)) = spec.split_once(':') else {
+            return Err(format!(
+                "Invalid type definition {:?}: expected name:glob",
+                spec
+            ));
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("Invalid type definition {:?}: empty name", spec));
+        }
+        for glob in globs.split(',').map(str::trim).filter(|g| !g.is_empty()) {
+            glob_to_extension(glob)?;
+            self.types
+                .entry(name.to_string())
+                .or_default()
+                .push(glob.to_string());
+        }
+        Ok(())
+    }
+
+    /// The `-e` style extensions (or bare file names) for a type.
+    pub fn extensions(&self, name: &str) -> Result<Vec<String>, String> {
+        let globs = self
+            .types
+            .get(name)
+            .ok_or_else(|| format!("Unknown type {:?} (see --type-list)", name))?;
+        globs.iter().map(|g| glob_to_extension(g)).collect()
+    }
+
+    /// One `name: glob, glob` line per type, for `--type-list`.
+    pub fn list(&self) -> String {
+        self.types
+            .iter()
+            .map(|(name, globs)| format!("{}: {}\n", name, globs.join(", ")))
+            .collect()
+    }
+}
+
+// "*.d.ts" -> "d.ts"; "Makefile" -> "Makefile"
+fn glob_to_extension(glob: &str) -> Result<String, String> {
+    let ext = glob.strip_prefix("*.").unwrap_or(glob);
+    if ext.is_empty() || ext.contains(['*', '?', '[', '/']) {
+        return Err(format!(
+            "Unsupported type glob {:?}: use *.ext or a file name",
+            glob
+        ));
+    }
+    Ok(ext.to_string())
+}
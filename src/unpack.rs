@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::paths::resolves_inside;
+
+/// One file recovered from a prompt (or a model response in the same format).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnpackedFile {
+    pub path: String,
+    pub content: String,
+}
+
+lazy_static! {
+    static ref XML_DOCUMENT: Regex = Regex::new(
+        r"(?s)<document\b[^>]*>\s*<source>(.*?)</source>\s*<document_content>\n(.*?)\n</document_content>\s*</document>"
+    )
+    .unwrap();
+    // `--meta` line between the path and the opening `---`
+    static ref META_LINE: Regex = Regex::new(r"^\[\w+=.*\]$").unwrap();
+}
+
+/// Parse files out of text in any of the formats we understand: the `<documents>`
+/// XML format, the default `---` format, or markdown code fences preceded by a line
+/// naming the file.
+pub fn parse_prompt(text: &str) -> Vec<UnpackedFile> {
+    if text.contains("<document_content>") {
+        return XML_DOCUMENT
+            .captures_iter(text)
+            .map(|c| UnpackedFile {
+                path: c[1].trim().to_string(),
+                content: c[2].to_string(),
+            })
+            .collect();
+    }
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut files = Vec::new();
+    let mut i = 0;
+    while i + 1 < lines.len() {
+        let header = lines[i].trim();
+        let next = lines[i + 1];
+        if header.is_empty() || header == "---" {
+            i += 1;
+        } else if let Some(header_len) = default_header_len(&lines[i + 1..]) {
+            // print_default: path, [metadata,] ---, content, ---
+            let start = i + 1 + header_len;
+            let end = (start..lines.len())
+                .find(|&j| lines[j] == "---" && ends_default_block(&lines, j))
+                .unwrap_or(lines.len());
+            files.push(UnpackedFile {
+                path: header.to_string(),
+                content: lines[start.min(end)..end].join("\n"),
+            });
+            i = end + 1;
+        } else if let Some(fence) = fence_marker(next) {
+            let start = i + 2;
+            let end = (start..lines.len())
+                .find(|&j| lines[j].trim_end() == fence)
+                .unwrap_or(lines.len());
+            let mut content = lines[start.min(end)..end].join("\n");
+            content.push('\n');
+            files.push(UnpackedFile {
+                path: clean_markdown_path(header),
+                content,
+            });
+            i = end + 1;
+        } else {
+            i += 1;
+        }
+    }
+    files
+}
+
+// A `---` line closes a default-format block if nothing but another block follows it,
+// or if it is the last one (so `--suffix` text can follow).
+fn ends_default_block(lines: &[&str], j: usize) -> bool {
+    let rest = &lines[j + 1..];
+    !rest.contains(&"---")
+        || (rest.len() >= 2
+            && !rest[0].trim().is_empty()
+            && default_header_len(&rest[1..]).is_some())
+}
+
+// What follows the path in a default-format block: `---`, or a `--meta` line and then
+// `---`. Returns how many lines that is.
+fn default_header_len(after_path: &[&str]) -> Option<usize> {
+    match after_path {
+        ["---", ..] => Some(1),
+        [meta, "---", ..] if META_LINE.is_match(meta) => Some(2),
+        _ => None,
+    }
+}
+
+// "```rust" opens a fence closed by "```"; longer backtick runs close with the same run.
+fn fence_marker(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let ticks = trimmed.len() - trimmed.trim_start_matches('`').len();
+    (ticks >= 3).then(|| &trimmed[..ticks])
+}
+
+// Markdown headers come in many shapes: "## src/main.rs", "**src/main.rs**",
+// "`src/main.rs`:", "File: src/main.rs".
+fn clean_markdown_path(line: &str) -> String {
+    let mut p = line.trim().trim_start_matches('#').trim();
+    if let Some(rest) = p.strip_prefix("File:") {
+        p = rest.trim();
+    }
+    p.trim_end_matches(':')
+        .trim_matches(|c| c == '*' || c == '`')
+        .trim()
+        .to_string()
+}
+
+/// What `unpack` did (or, in a dry run, would do) with each file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UnpackAction {
+    Create,
+    Overwrite,
+    /// The file exists and `overwrite` was not set.
+    SkipExisting,
+    /// The path is absolute or climbs out of the target directory.
+    RejectUnsafe,
+}
+
+impl UnpackAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            UnpackAction::Create => "create",
+            UnpackAction::Overwrite => "overwrite",
+            UnpackAction::SkipExisting => "skip (exists)",
+            UnpackAction::RejectUnsafe => "reject (unsafe path)",
+        }
+    }
+}
+
+/// Write `files` under `target`. Paths that are absolute, contain `..` or lead
+/// through a symlink out of `target` are never written; existing files are only replaced when `overwrite` is set.
+pub fn unpack_files(
+    files: &[UnpackedFile],
+    target: &Path,
+    dry_run: bool,
+    overwrite: bool,
+) -> Result<Vec<(String, UnpackAction)>, Box<dyn Error>> {
+    let mut report = Vec::new();
+    for file in files {
+        let Some(dest) = safe_join(target, &file.path).filter(|d| resolves_inside(d, target))
+        else {
+            report.push((file.path.clone(), UnpackAction::RejectUnsafe));
+            continue;
+        };
+        let action = if !dest.exists() {
+            UnpackAction::Create
+        } else if overwrite {
+            UnpackAction::Overwrite
+        } else {
+            UnpackAction::SkipExisting
+        };
+        if !dry_run && matches!(action, UnpackAction::Create | UnpackAction::Overwrite) {
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&dest, &file.content)
+                .map_err(|e| format!("Cannot write {}: {}", dest.display(), e))?;
+        }
+        report.push((file.path.clone(), action));
+    }
+    Ok(report)
+}
+
+/// Join a relative path from untrusted input onto `root`, refusing anything that
+/// could land outside it.
+pub fn safe_join(root: &Path, relative: &str) -> Option<PathBuf> {
+    let rel = Path::new(relative);
+    if relative.is_empty() {
+        return None;
+    }
+    let mut out = root.to_path_buf();
+    for comp in rel.components() {
+        match comp {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,173 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::error::Result;
+
+/// How long things have to stay quiet after a change before the output is rebuilt,
+/// so an editor saving several files (or writing one in steps) causes a single run.
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// File contents kept between runs in watch mode. A file is only read again when its
+/// size or modification time changed, or a watch event named it.
+#[derive(Debug, Default)]
+pub struct ContentCache {
+    entries: RefCell<HashMap<PathBuf, CachedFile>>,
+}
+
+#[derive(Debug)]
+struct CachedFile {
+    len: u64,
+    modified: SystemTime,
+    content: String,
+}
+
+impl ContentCache {
+    pub fn read(&self, path: &Path) -> io::Result<String> {
+        let meta = fs::metadata(path)?;
+        let modified = meta.modified()?;
+        if let Some(cached) = self.entries.borrow().get(path) {
+            if cached.len == meta.len() && cached.modified == modified {
+                return Ok(cached.content.clone());
+            }
+        }
+        let content = fs::read_to_string(path)?;
+        self.entries.borrow_mut().insert(
+            path.to_path_buf(),
+            CachedFile {
+                len: meta.len(),
+                modified,
+                content: content.clone(),
+            },
+        );
+        Ok(content)
+    }
+
+    /// Forget the given (canonical) paths, in case a change landed within the file
+    /// system's timestamp resolution.
+    pub fn invalidate(&self, changed: &[PathBuf]) {
+        self.entries.borrow_mut().retain(|path, _| {
+            fs::canonicalize(path).is_ok_and(|canonical| !changed.contains(&canonical))
+        });
+    }
+}
+
+/// Watches the input paths (files or directories) for changes. Changes to the output
+/// file itself and anything inside `.git` are ignored.
+pub struct InputWatcher {
+    watcher: RecommendedWatcher,
+    events: mpsc::Receiver<notify::Result<Event>>,
+    // What is registered with `watcher`: directories, and how deep
+    watched: HashMap<PathBuf, RecursiveMode>,
+    // Canonical input paths; only changes under these count
+    roots: Vec<PathBuf>,
+    output: PathBuf,
+}
+
+impl InputWatcher {
+    pub fn new(output: &Path) -> Result<InputWatcher> {
+        let (tx, events) = mpsc::channel();
+        let watcher =
+            notify::recommended_watcher(tx).map_err(|e| format!("Can't watch files: {}", e))?;
+        Ok(InputWatcher {
+            watcher,
+            events,
+            watched: HashMap::new(),
+            roots: Vec::new(),
+            output: absolutize_output(output),
+        })
+    }
+
+    /// Watch exactly these paths from now on. Paths that don't exist are left out.
+    pub fn watch_paths(&mut self, paths: &[String]) -> Result<()> {
+        // Editors often save by replacing the file, so single files are watched
+        // through their directory
+        let mut targets: HashMap<PathBuf, RecursiveMode> = HashMap::new();
+        let mut roots = Vec::new();
+        for path in paths {
+            let Ok(root) = fs::canonicalize(path) else {
+                continue;
+            };
+            let (target, mode) = if root.is_dir() {
+                (root.clone(), RecursiveMode::Recursive)
+            } else {
+                let parent = root.parent().unwrap_or(&root).to_path_buf();
+                (parent, RecursiveMode::NonRecursive)
+            };
+            let entry = targets.entry(target).or_insert(mode);
+            if mode == RecursiveMode::Recursive {
+                *entry = mode;
+            }
+            roots.push(root);
+        }
+
+        for (target, mode) in &self.watched {
+            if targets.get(target) != Some(mode) {
+                // It may already be gone along with the directory
+                let _ = self.watcher.unwatch(target);
+            }
+        }
+        for (target, mode) in &targets {
+            if self.watched.get(target) != Some(mode) {
+                self.watcher
+                    .watch(target, *mode)
+                    .map_err(|e| format!("Can't watch {}: {}", target.display(), e))?;
+            }
+        }
+        self.watched = targets;
+        self.roots = roots;
+        Ok(())
+    }
+
+    /// Block until something relevant changes and things have been quiet for
+    /// `DEBOUNCE`, then return the changed files. Returns None once the watcher
+    /// stops.
+    pub fn next_changes(&self) -> Option<Vec<PathBuf>> {
+        loop {
+            let mut changed: Vec<PathBuf> = Vec::new();
+            let mut event = Some(self.events.recv().ok()?);
+            while let Some(result) = event {
+                match result {
+                    Ok(event) => {
+                        for path in event.paths {
+                            if self.is_relevant(&path) && !changed.contains(&path) {
+                                changed.push(path);
+                            }
+                        }
+                    }
+                    Err(e) => eprintln!("Warning: {}", e),
+                }
+                event = self.events.recv_timeout(DEBOUNCE).ok();
+            }
+            if !changed.is_empty() {
+                return Some(changed);
+            }
+        }
+    }
+
+    fn is_relevant(&self, path: &Path) -> bool {
+        path != self.output
+            && !path
+                .components()
+                .any(|c| c == Component::Normal(".git".as_ref()))
+            && self.roots.iter().any(|root| path.starts_with(root))
+    }
+}
+
+// The output file may not exist yet, so canonicalize its directory instead.
+fn absolutize_output(output: &Path) -> PathBuf {
+    let dir = match output.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match (fs::canonicalize(dir), output.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => output.to_path_buf(),
+    }
+}